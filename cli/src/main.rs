@@ -1,10 +1,17 @@
-use std::fmt::{Arguments, Display};
+use std::{
+    error::Error,
+    fmt::{Arguments, Debug, Display},
+    path::PathBuf,
+};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use trex_parser::{Color, error::Result, Format, Regex, Style};
+use trex_parser::{
+    ast::Span, compiler, generate::Generator, lint, Charset, Color, Format, Layout, Regex,
+    RenderOptions, Style, Theme, BUILTIN_THEMES,
+};
 
-const _TOML: &'static str = include_str!("../Cargo.toml");
+const _TOML: &str = include_str!("../Cargo.toml");
 
 /// ────────────████████{n}
 /// ──────────███▄███████{n}
@@ -20,6 +27,12 @@ const _TOML: &'static str = include_str!("../Cargo.toml");
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// Color theme: dark, light, high-contrast, or the name of a TOML file in $XDG_CONFIG_HOME/trex/
+    #[arg(long, global = true)]
+    theme: Option<String>,
+    /// When to color the output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
     #[command(subcommand)]
     command: Commands,
 }
@@ -33,7 +46,158 @@ enum Commands {
         ignore_case: bool,
         #[arg(short, long)]
         multiline: bool,
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Wraps the text diagram to this many columns, the width of the terminal by default
+        #[arg(short, long)]
+        width: Option<usize>,
+        /// Characters the text diagram is drawn with
+        #[arg(long, value_enum, default_value_t = Glyphs::Rounded)]
+        charset: Glyphs,
+        /// How much room the text diagram takes
+        #[arg(long, value_enum, default_value_t = DiagramLayout::Expanded)]
+        layout: DiagramLayout,
+        /// Highlights the path the match of this input takes through the text diagram
+        #[arg(long)]
+        input: Option<String>,
+    },
+    /// Simplifies a regular expression and compares the diagrams
+    Simplify { expression: String },
+    /// Compiles a regular expression to a finite automaton
+    Compile {
+        expression: String,
+        #[arg(short, long, value_enum, default_value_t = Automaton::Dfa)]
+        automaton: Automaton,
+        #[arg(short, long, value_enum, default_value_t = AutomatonFormat::Text)]
+        format: AutomatonFormat,
+    },
+    /// Checks whether two regular expressions match the same strings
+    Equiv { first: String, second: String },
+    /// Checks whether the first expression is strictly narrower than the second: every string it
+    /// matches is matched by the second, which matches more
+    Subset { first: String, second: String },
+    /// Generates strings matched by a regular expression
+    Generate {
+        expression: String,
+        /// Number of strings to generate
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+        /// Pick strings at random instead of listing the shortest ones
+        #[arg(short, long)]
+        random: bool,
+        /// Seed of the random picks, the current time by default
+        #[arg(short, long)]
+        seed: Option<u64>,
+        /// Most repetitions of quantifiers without an upper bound
+        #[arg(long, default_value_t = 3)]
+        max_repeat: usize,
+        /// Generate rejected strings close to matched ones instead
+        #[arg(long, conflicts_with = "random")]
+        negative: bool,
     },
+    /// Describes a regular expression in plain English
+    Explain { expression: String },
+    /// Checks regular expressions for frequent mistakes
+    Lint {
+        #[arg(required = true)]
+        expressions: Vec<String>,
+        /// Print the expressions with every automatic fix applied
+        #[arg(long)]
+        fix: bool,
+        /// Numbers or names of the capturing groups in use, the others are reported
+        #[arg(long, value_delimiter = ',')]
+        uses: Option<Vec<String>>,
+    },
+    /// Looks for constructs prone to catastrophic backtracking
+    Audit {
+        expression: String,
+        /// Print attack strings in full instead of as a formula
+        #[arg(long)]
+        full: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Box-drawing diagram for the terminal
+    Text,
+    /// Standalone SVG document
+    Svg,
+    /// Graphviz DOT graph of the syntax tree
+    Dot,
+    /// Syntax tree and source spans as JSON, see `trex_parser::schema`
+    Json,
+    /// Standalone HTML page linking the diagram to the pattern
+    Html,
+    /// Mermaid flowchart
+    Mermaid,
+    /// PlantUML activity diagram
+    Plantuml,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Glyphs {
+    /// Box-drawing characters with rounded corners
+    Rounded,
+    /// Box-drawing characters with square corners
+    Square,
+    /// Plain ASCII, `+-|`
+    Ascii,
+}
+
+impl From<Glyphs> for Charset {
+    fn from(glyphs: Glyphs) -> Self {
+        match glyphs {
+            Glyphs::Rounded => Charset::Rounded,
+            Glyphs::Square => Charset::Square,
+            Glyphs::Ascii => Charset::Ascii,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DiagramLayout {
+    /// Loops around quantified tokens and every alternative on its own line
+    Expanded,
+    /// Quantifiers as suffixes like `a{2,4}` and single characters in `[abc]` boxes
+    Compact,
+}
+
+impl From<DiagramLayout> for Layout {
+    fn from(layout: DiagramLayout) -> Self {
+        match layout {
+            DiagramLayout::Expanded => Layout::Expanded,
+            DiagramLayout::Compact => Layout::Compact,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorChoice {
+    /// When writing to a terminal, unless NO_COLOR is set or CLICOLOR_FORCE forces colors
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Automaton {
+    /// Thompson automaton, with epsilon edges
+    Nfa,
+    /// Minimal deterministic automaton
+    Dfa,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum AutomatonFormat {
+    /// List of transitions
+    Text,
+    /// Graphviz DOT graph
+    Dot,
+    /// Mermaid state diagram
+    Mermaid,
+    /// PlantUML state diagram
+    Plantuml,
 }
 
 fn to_termion_color(color: &Color) -> Box<dyn termion::color::Color> {
@@ -41,6 +205,7 @@ fn to_termion_color(color: &Color) -> Box<dyn termion::color::Color> {
         Color::Reset => Box::new(termion::color::Reset),
         Color::Black => Box::new(termion::color::Black),
         Color::Red => Box::new(termion::color::Red),
+        Color::Green => Box::new(termion::color::Green),
         Color::Yellow => Box::new(termion::color::Yellow),
         Color::Blue => Box::new(termion::color::Blue),
         Color::Magenta => Box::new(termion::color::Magenta),
@@ -54,6 +219,8 @@ fn to_termion_color(color: &Color) -> Box<dyn termion::color::Color> {
         Color::LightMagenta => Box::new(termion::color::LightMagenta),
         Color::LightCyan => Box::new(termion::color::LightCyan),
         Color::LightWhite => Box::new(termion::color::LightWhite),
+        Color::Ansi256(value) => Box::new(termion::color::AnsiValue(*value)),
+        Color::Rgb(r, g, b) => Box::new(termion::color::Rgb(*r, *g, *b)),
     }
 }
 
@@ -136,14 +303,436 @@ fn termion_style(style: &Style, arg: &Arguments<'_>) -> String {
     }
 }
 
-fn main() -> Result<()> {
+/// Writes styled text with termion escape codes, or as is when colors are
+/// off. All the styled output goes through it.
+#[derive(Debug, Clone, Copy)]
+struct Painter {
+    colors: bool,
+}
+
+impl Painter {
+    fn new(choice: ColorChoice) -> Self {
+        let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+        let colors = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if set("NO_COLOR") => false,
+            ColorChoice::Auto
+                if set("CLICOLOR_FORCE")
+                    && std::env::var_os("CLICOLOR_FORCE") != Some("0".into()) =>
+            {
+                true
+            }
+            ColorChoice::Auto => termion::is_tty(&std::io::stdout()),
+        };
+
+        Self { colors }
+    }
+
+    /// The style function of [`Regex::with_options`] and the like, which
+    /// resets the style at the end of every line itself.
+    fn style(self) -> impl Fn(&Style, &Arguments<'_>) -> String {
+        move |style, arg| match self.colors {
+            true => termion_style(style, arg),
+            false => arg.to_string(),
+        }
+    }
+
+    /// `text` in `style`, followed by a reset.
+    fn paint(self, style: &Style, text: impl Display) -> String {
+        match self.colors {
+            true => format!(
+                "{}{}",
+                termion_style(style, &format_args!("{text}")),
+                termion::style::Reset
+            ),
+            false => text.to_string(),
+        }
+    }
+}
+
+/// Where user themes are looked for: `$XDG_CONFIG_HOME/trex`, or
+/// `~/.config/trex` when it is not set.
+fn config_dir() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config.join("trex"))
+}
+
+/// A theme that could not be loaded, shown as its message when returned from
+/// `main`.
+struct ThemeError(String);
+
+impl Debug for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ThemeError {}
+
+/// The theme called `name`, built-in or read from `<name>.toml` in the
+/// configuration directory. Without a name, `theme.toml` there is used if it
+/// exists, the dark theme otherwise.
+fn load_theme(name: Option<&str>) -> Result<Theme, ThemeError> {
+    if let Some(theme) = name.and_then(Theme::builtin) {
+        return Ok(theme);
+    }
+
+    let path = match (config_dir(), name) {
+        (Some(dir), Some(name)) => dir.join(format!("{name}.toml")),
+        (Some(dir), None) if dir.join("theme.toml").exists() => dir.join("theme.toml"),
+        (_, None) => return Ok(Theme::default()),
+        (None, Some(name)) => {
+            return Err(ThemeError(format!(
+                "unknown theme `{name}`, expected one of {}",
+                BUILTIN_THEMES.join(", ")
+            )))
+        }
+    };
+    let text = std::fs::read_to_string(&path).map_err(|err| match name {
+        Some(name) if err.kind() == std::io::ErrorKind::NotFound => ThemeError(format!(
+            "unknown theme `{name}`, expected one of {} or a file at {}",
+            BUILTIN_THEMES.join(", "),
+            path.display()
+        )),
+        _ => ThemeError(format!("{}: {err}", path.display())),
+    })?;
+
+    toml::from_str(&text).map_err(|err| ThemeError(format!("{}: {err}", path.display())))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    let painter = Painter::new(cli.color);
 
     match cli.command {
-        Commands::Parse { expression, .. } => {
+        Commands::Parse {
+            expression,
+            format,
+            width,
+            charset,
+            layout,
+            input,
+            ..
+        } => {
+            let re: Regex = expression.parse()?;
+
+            match format {
+                OutputFormat::Text => {
+                    let theme = load_theme(cli.theme.as_deref())?;
+                    let trace = input.as_deref().map(|input| re.trace(input));
+                    let options = RenderOptions {
+                        max_width: width
+                            .or_else(|| termion::terminal_size().ok().map(|(cols, _)| cols.into())),
+                        charset: charset.into(),
+                        layout: layout.into(),
+                        theme,
+                        trace: trace.clone().and_then(Result::ok).flatten(),
+                    };
+
+                    println!("{}", re.with_options(&options, painter.style()));
+
+                    match (input, trace) {
+                        (Some(_), Some(Ok(Some(trace)))) => {
+                            println!(
+                                "matched {:?} at {}..{}",
+                                trace.matched(),
+                                trace.start,
+                                trace.end
+                            )
+                        }
+                        (Some(input), Some(Ok(None))) => {
+                            eprintln!("no match in {input:?}");
+                            std::process::exit(1);
+                        }
+                        (Some(input), Some(Err(err))) => {
+                            eprintln!("{err} looking for a match in {input:?}");
+                            std::process::exit(1);
+                        }
+                        _ => {}
+                    }
+                }
+                OutputFormat::Svg => print!("{}", re.to_svg()),
+                OutputFormat::Dot => print!("{}", re.to_dot()),
+                OutputFormat::Html => print!("{}", re.to_html()),
+                OutputFormat::Mermaid => print!("{}", re.to_mermaid()),
+                OutputFormat::Plantuml => print!("{}", re.to_plantuml()),
+                OutputFormat::Json => {
+                    let json =
+                        serde_json::to_string_pretty(&re.to_document()).expect("tokens serialize");
+                    println!("{json}");
+                }
+            }
+
+            Ok(())
+        }
+        Commands::Simplify { expression } => {
+            let re: Regex = expression.parse()?;
+            let options = RenderOptions {
+                theme: load_theme(cli.theme.as_deref())?,
+                ..Default::default()
+            };
+            let simplified = re.simplify();
+            println!("{}\n", simplified.ast());
+            println!(
+                "{}",
+                re.compare_with_options(&simplified, &options, painter.style())
+            );
+            Ok(())
+        }
+        Commands::Compile {
+            expression,
+            automaton,
+            format,
+        } => {
+            let re: Regex = expression.parse()?;
+
+            match (automaton, format) {
+                (Automaton::Nfa, AutomatonFormat::Dot) => {
+                    print!("{}", compiler::Nfa::new(re.ast()).to_dot())
+                }
+                (Automaton::Dfa, AutomatonFormat::Dot) => print!("{}", re.compile().to_dot()),
+                (Automaton::Nfa, AutomatonFormat::Mermaid) => {
+                    print!("{}", compiler::Nfa::new(re.ast()).to_mermaid())
+                }
+                (Automaton::Dfa, AutomatonFormat::Mermaid) => {
+                    print!("{}", re.compile().to_mermaid())
+                }
+                (Automaton::Nfa, AutomatonFormat::Plantuml) => {
+                    print!("{}", compiler::Nfa::new(re.ast()).to_plantuml())
+                }
+                (Automaton::Dfa, AutomatonFormat::Plantuml) => {
+                    print!("{}", re.compile().to_plantuml())
+                }
+                (Automaton::Nfa, AutomatonFormat::Text) => {
+                    let nfa = compiler::Nfa::new(re.ast());
+
+                    println!("start {}, accepting {}", nfa.start(), nfa.accept());
+
+                    for (from, state) in nfa.states().iter().enumerate() {
+                        for (edge, to) in &state.transitions {
+                            println!("{from} -> {to} on {}", edge.label());
+                        }
+                    }
+                }
+                (Automaton::Dfa, AutomatonFormat::Text) => {
+                    let dfa = re.compile();
+                    let accepting: Vec<String> = (0..dfa.state_count())
+                        .filter(|state| dfa.is_accepting(*state))
+                        .map(|state| state.to_string())
+                        .collect();
+
+                    println!("start {}, accepting {}", dfa.start(), accepting.join(" "));
+
+                    for (from, label, to) in dfa.edges() {
+                        println!("{from} -> {to} on {label}");
+                    }
+                }
+            }
+
+            Ok(())
+        }
+        Commands::Equiv { first, second } => {
+            let a: Regex = first.parse()?;
+            let b: Regex = second.parse()?;
+
+            match compiler::counterexample(&a.compile(), &b.compile()) {
+                None => {
+                    println!("equivalent");
+                    Ok(())
+                }
+                Some(counterexample) => {
+                    let (accepted, rejected) = match counterexample.accepted_by_first {
+                        true => (first, second),
+                        false => (second, first),
+                    };
+                    println!("not equivalent");
+                    println!(
+                        "{:?} is matched by {accepted} but not by {rejected}",
+                        counterexample.input
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Subset { first, second } => {
+            let a = first.parse::<Regex>()?.compile();
+            let b = second.parse::<Regex>()?.compile();
+            let extra = compiler::difference(&a, &b);
+
+            match extra.shortest_accepted() {
+                None if compiler::is_subset(&b, &a) => {
+                    println!("not strictly narrower (equivalent)");
+                    std::process::exit(1);
+                }
+                None => println!("strict subset"),
+                Some(input) => {
+                    println!("not a subset");
+                    println!("{input:?} is matched by {first} but not by {second}");
+                    println!("matched only by {first}: {}", extra.to_token());
+                    std::process::exit(1);
+                }
+            }
+
+            Ok(())
+        }
+        Commands::Generate {
+            expression,
+            count,
+            random,
+            seed,
+            max_repeat,
+            negative,
+        } => {
             let re: Regex = expression.parse()?;
-            println!("{}", re.with_style(termion_style));
+            let generator = Generator { max_repeat };
+
+            if negative {
+                for miss in generator.near_misses(re.ast(), count) {
+                    println!(
+                        "{:?}\trejected by {}: {}, close to {:?}",
+                        miss.input, miss.token, miss.mutation, miss.matched,
+                    );
+                }
+
+                return Ok(());
+            }
+
+            let examples = match random {
+                true => generator.sample(re.ast(), count, seed.unwrap_or_else(time_seed)),
+                false => generator.shortest(re.ast(), count),
+            };
+
+            for example in examples {
+                println!("{example:?}");
+            }
+
+            Ok(())
+        }
+        Commands::Explain { expression } => {
+            let re: Regex = expression.parse()?;
+            println!("{}", re.explain());
+            Ok(())
+        }
+        Commands::Lint {
+            expressions,
+            fix,
+            uses,
+        } => {
+            let theme = load_theme(cli.theme.as_deref())?;
+            let options = lint::Options {
+                used_captures: uses,
+            };
+            let mut found = false;
+
+            for expression in expressions {
+                let re: Regex = match expression.parse() {
+                    Ok(re) => re,
+                    Err(err) => {
+                        eprintln!(
+                            "{}: {expression}: {err}",
+                            painter.paint(&theme.error.into(), "error")
+                        );
+                        found = true;
+                        continue;
+                    }
+                };
+                let diagnostics = re.lint(&options);
+
+                if fix {
+                    println!("{}", lint::apply_fixes(&expression, &diagnostics));
+                    continue;
+                }
+
+                for diagnostic in &diagnostics {
+                    println!(
+                        "{}[{}]: {}",
+                        diagnostic.rule.severity, diagnostic.rule.id, diagnostic.message
+                    );
+
+                    if let Some(span) = diagnostic.span {
+                        print_span(
+                            &expression,
+                            span,
+                            painter.paint(&theme.error.into(), "^".repeat(span.len().max(1))),
+                        );
+                    }
+                    if let Some(fix) = &diagnostic.fix {
+                        match fix.replacement.as_str() {
+                            "" => println!("  fix: remove it"),
+                            replacement => println!("  fix: replace with `{replacement}`"),
+                        }
+                    }
+                }
+
+                found |= !diagnostics.is_empty();
+            }
+
+            if found && !fix {
+                std::process::exit(1);
+            }
+
             Ok(())
         }
+        Commands::Audit { expression, full } => {
+            let theme = load_theme(cli.theme.as_deref())?;
+            let re: Regex = expression.parse()?;
+            let findings = re.audit();
+
+            if findings.is_empty() {
+                println!("no backtracking risk found");
+                return Ok(());
+            }
+
+            for finding in findings {
+                match finding.span {
+                    Some(span) => {
+                        let source: String = expression
+                            .chars()
+                            .skip(span.start)
+                            .take(span.len())
+                            .collect();
+
+                        println!("{}: {} {source}", finding.severity, finding.risk);
+                        print_span(
+                            &expression,
+                            span,
+                            painter.paint(&theme.error.into(), "^".repeat(span.len().max(1))),
+                        );
+                    }
+                    None => println!("{}: {} {}", finding.severity, finding.risk, finding.token),
+                }
+
+                match full {
+                    true => println!("  attack: {:?}", finding.attack.build()),
+                    false => println!("  attack: {}", finding.attack),
+                }
+            }
+
+            std::process::exit(1);
+        }
     }
 }
+
+/// Prints `expression` with `carets` under `span`.
+fn print_span(expression: &str, span: Span, carets: String) {
+    println!("  {expression}");
+    println!("  {}{carets}", " ".repeat(span.start));
+}
+
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,451 @@
+//! The syntax tree produced by the parser.
+//!
+//! A parsed expression is a single [`Token`]. Composite tokens own their
+//! children, so the whole expression can be walked with a [`Visitor`] or
+//! rewritten in place with a [`VisitorMut`]. Its `Display` implementation
+//! writes the tree back as a pattern the parser accepts.
+
+use std::fmt::{self, Display, Write};
+
+/// A node of the regular expression syntax tree.
+///
+/// With the `serde` feature, tokens serialize as described in
+/// [`crate::schema`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "args"))]
+#[non_exhaustive]
+pub enum Token {
+    /// A capturing group `(...)`, with its name when written `(?<name>...)`.
+    Capturing(Box<Token>, Option<String>),
+    /// A sequence of tokens that must match one after the other.
+    Conjunction(Vec<Token>),
+    /// Alternatives, either from `a|b` or from a character class `[ab]`.
+    Disjunction(Vec<Token>),
+    /// A single character matched literally.
+    Literal(char),
+    /// The start anchor `^`.
+    Start,
+    /// The end anchor `$`.
+    End,
+    /// Any character, `.`.
+    Any,
+    /// A token repeated between `min` and `max` times, as many times as possible.
+    GreedyQuantifier(Box<Token>, usize, Option<usize>),
+    /// A token repeated between `min` and `max` times, as few times as possible.
+    LazyQuantifier(Box<Token>, usize, Option<usize>),
+    /// A word boundary, `\b`.
+    WordBoundary,
+    /// A word character, `\w`.
+    Alphanumeric,
+    /// A digit, `\d`.
+    Digit,
+    /// A whitespace character, `\s`.
+    Whitespace,
+    /// Anything but a word character, `\W`.
+    NotAlphanumeric,
+    /// Anything but a digit, `\D`.
+    NotDigit,
+    /// Anything but a whitespace character, `\S`.
+    NotWhitespace,
+    /// An inclusive character range inside a class, `a-z`.
+    AsciiRange(char, char),
+}
+
+impl Token {
+    /// The direct children of this token, in source order.
+    pub fn children(&self) -> &[Token] {
+        match self {
+            Token::Capturing(tok, _)
+            | Token::GreedyQuantifier(tok, _, _)
+            | Token::LazyQuantifier(tok, _, _) => std::slice::from_ref(tok.as_ref()),
+            Token::Conjunction(tokens) | Token::Disjunction(tokens) => tokens,
+            _ => &[],
+        }
+    }
+
+    /// The direct children of this token, in source order.
+    pub fn children_mut(&mut self) -> &mut [Token] {
+        match self {
+            Token::Capturing(tok, _)
+            | Token::GreedyQuantifier(tok, _, _)
+            | Token::LazyQuantifier(tok, _, _) => std::slice::from_mut(tok.as_mut()),
+            Token::Conjunction(tokens) | Token::Disjunction(tokens) => tokens,
+            _ => &mut [],
+        }
+    }
+
+    /// Whether this token can appear inside a character class.
+    pub fn is_class_item(&self) -> bool {
+        matches!(
+            self,
+            Token::Literal(_)
+                | Token::AsciiRange(_, _)
+                | Token::Alphanumeric
+                | Token::Digit
+                | Token::Whitespace
+                | Token::NotAlphanumeric
+                | Token::NotDigit
+                | Token::NotWhitespace
+        )
+    }
+
+    /// Whether this token is a character class, i.e. a disjunction of single
+    /// characters such as `[a-z_]`.
+    pub fn is_class(&self) -> bool {
+        match self {
+            Token::Disjunction(tokens) => tokens.iter().all(Token::is_class_item),
+            _ => false,
+        }
+    }
+
+    /// Walks the tree depth first, calling the visitor before and after the
+    /// children of every token.
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_pre(self);
+
+        for child in self.children() {
+            child.walk(visitor);
+        }
+
+        visitor.visit_post(self);
+    }
+
+    /// Walks the tree depth first, letting the visitor rewrite every token.
+    ///
+    /// Children are walked after `visit_pre` returns, so a token replaced in
+    /// the pre-order hook has its new children visited.
+    pub fn walk_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_pre(self);
+
+        for child in self.children_mut() {
+            child.walk_mut(visitor);
+        }
+
+        visitor.visit_post(self);
+    }
+}
+
+// Binding strength used to decide where `(?:...)` is needed when printing.
+const ALTERNATION: u8 = 0;
+const SEQUENCE: u8 = 1;
+const QUANTIFIED: u8 = 2;
+const ATOM: u8 = 3;
+
+fn precedence(tok: &Token) -> u8 {
+    match tok {
+        Token::Conjunction(tokens) if tokens.len() == 1 => precedence(&tokens[0]),
+        Token::Conjunction(_) => SEQUENCE,
+        Token::Disjunction(_) if tok.is_class() => ATOM,
+        Token::Disjunction(_) => ALTERNATION,
+        Token::GreedyQuantifier(_, _, _) | Token::LazyQuantifier(_, _, _) => QUANTIFIED,
+        _ => ATOM,
+    }
+}
+
+/// Whether `ch` can be written as is, as opposed to controls, invisible
+/// whitespace, private use characters and noncharacters.
+fn is_printable(ch: char) -> bool {
+    !(ch.is_control()
+        || (ch.is_whitespace() && ch != ' ')
+        || ('\u{E000}'..='\u{F8FF}').contains(&ch)
+        || ('\u{FFF0}'..='\u{FFFF}').contains(&ch)
+        || ch >= '\u{F0000}'
+        || (ch as u32) & 0xFFFE == 0xFFFE)
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, ch: char, special: &str) -> fmt::Result {
+    match ch {
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        _ if special.contains(ch) => write!(f, "\\{ch}"),
+        _ if !is_printable(ch) => write!(f, "\\u{{{:x}}}", ch as u32),
+        _ => f.write_char(ch),
+    }
+}
+
+fn write_class_item(f: &mut fmt::Formatter<'_>, tok: &Token) -> fmt::Result {
+    const SPECIAL: &str = "\\[]-";
+
+    match tok {
+        Token::Literal(ch) => write_escaped(f, *ch, SPECIAL),
+        Token::AsciiRange(start, end) => {
+            write_escaped(f, *start, SPECIAL)?;
+            f.write_char('-')?;
+            write_escaped(f, *end, SPECIAL)
+        }
+        tok => write!(f, "{tok}"),
+    }
+}
+
+/// How a quantifier is written after its token, such as `+` or `{2,4}?`.
+pub(crate) fn quantifier_suffix(min: usize, max: Option<usize>, lazy: bool) -> String {
+    let mut suffix = match (min, max) {
+        (0, Some(1)) if lazy => "{0,1}".to_owned(),
+        (0, Some(1)) => "?".to_owned(),
+        (0, None) => "*".to_owned(),
+        (1, None) => "+".to_owned(),
+        (min, None) => format!("{{{min},}}"),
+        (min, Some(max)) if min == max => format!("{{{min}}}"),
+        (min, Some(max)) => format!("{{{min},{max}}}"),
+    };
+
+    if lazy {
+        suffix.push('?');
+    }
+
+    suffix
+}
+
+fn write_quantifier(
+    f: &mut fmt::Formatter<'_>,
+    tok: &Token,
+    min: usize,
+    max: Option<usize>,
+    lazy: bool,
+) -> fmt::Result {
+    write_token(f, tok, ATOM)?;
+    f.write_str(&quantifier_suffix(min, max, lazy))
+}
+
+fn write_token(f: &mut fmt::Formatter<'_>, tok: &Token, min_precedence: u8) -> fmt::Result {
+    if precedence(tok) < min_precedence {
+        f.write_str("(?:")?;
+        write_token(f, tok, ALTERNATION)?;
+        return f.write_char(')');
+    }
+
+    match tok {
+        Token::Capturing(tok, None) => {
+            f.write_char('(')?;
+            write_token(f, tok, ALTERNATION)?;
+            f.write_char(')')
+        }
+        Token::Capturing(tok, Some(name)) => {
+            write!(f, "(?<{name}>")?;
+            write_token(f, tok, ALTERNATION)?;
+            f.write_char(')')
+        }
+        Token::Conjunction(tokens) => {
+            for tok in tokens {
+                write_token(f, tok, SEQUENCE)?;
+            }
+            Ok(())
+        }
+        Token::Disjunction(tokens) if tok.is_class() => {
+            f.write_char('[')?;
+            for tok in tokens {
+                write_class_item(f, tok)?;
+            }
+            f.write_char(']')
+        }
+        Token::Disjunction(tokens) => {
+            for (i, tok) in tokens.iter().enumerate() {
+                if i > 0 {
+                    f.write_char('|')?;
+                }
+                write_token(f, tok, ALTERNATION)?;
+            }
+            Ok(())
+        }
+        Token::Literal(ch) => write_escaped(f, *ch, "\\.*+?[](){}|^$"),
+        Token::Start => f.write_char('^'),
+        Token::End => f.write_char('$'),
+        Token::Any => f.write_char('.'),
+        Token::GreedyQuantifier(tok, min, max) => write_quantifier(f, tok, *min, *max, false),
+        Token::LazyQuantifier(tok, min, max) => write_quantifier(f, tok, *min, *max, true),
+        Token::WordBoundary => f.write_str("\\b"),
+        Token::Alphanumeric => f.write_str("\\w"),
+        Token::Digit => f.write_str("\\d"),
+        Token::Whitespace => f.write_str("\\s"),
+        Token::NotAlphanumeric => f.write_str("\\W"),
+        Token::NotDigit => f.write_str("\\D"),
+        Token::NotWhitespace => f.write_str("\\S"),
+        Token::AsciiRange(_, _) => {
+            f.write_char('[')?;
+            write_class_item(f, tok)?;
+            f.write_char(']')
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_token(f, self, ALTERNATION)
+    }
+}
+
+/// Read-only traversal of a [`Token`] tree, see [`Token::walk`].
+pub trait Visitor {
+    /// Called before the children of `tok` are visited.
+    fn visit_pre(&mut self, _tok: &Token) {}
+
+    /// Called after the children of `tok` have been visited.
+    fn visit_post(&mut self, _tok: &Token) {}
+}
+
+/// Rewriting traversal of a [`Token`] tree, see [`Token::walk_mut`].
+pub trait VisitorMut {
+    /// Called before the children of `tok` are visited.
+    fn visit_pre(&mut self, _tok: &mut Token) {}
+
+    /// Called after the children of `tok` have been visited.
+    fn visit_post(&mut self, _tok: &mut Token) {}
+}
+
+/// A range of character offsets in the source of an expression, from the
+/// first character of a token to just past its last one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_expr;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Visitor for Recorder {
+        fn visit_pre(&mut self, tok: &Token) {
+            self.events.push(format!("pre {}", name(tok)));
+        }
+
+        fn visit_post(&mut self, tok: &Token) {
+            self.events.push(format!("post {}", name(tok)));
+        }
+    }
+
+    fn name(tok: &Token) -> &'static str {
+        match tok {
+            Token::Conjunction(_) => "conjunction",
+            Token::Capturing(_, _) => "capturing",
+            Token::GreedyQuantifier(_, _, _) => "quantifier",
+            Token::Literal(_) => "literal",
+            _ => "other",
+        }
+    }
+
+    #[test]
+    fn test_walk_order() {
+        let tok = Token::Conjunction(vec![
+            Token::Literal('a'),
+            Token::GreedyQuantifier(
+                Box::new(Token::Capturing(Box::new(Token::Literal('b')), None)),
+                1,
+                None,
+            ),
+        ]);
+        let mut recorder = Recorder::default();
+
+        tok.walk(&mut recorder);
+
+        assert_eq!(
+            recorder.events,
+            vec![
+                "pre conjunction",
+                "pre literal",
+                "post literal",
+                "pre quantifier",
+                "pre capturing",
+                "pre literal",
+                "post literal",
+                "post capturing",
+                "post quantifier",
+                "post conjunction",
+            ]
+        );
+    }
+
+    struct Uppercase;
+
+    impl VisitorMut for Uppercase {
+        fn visit_post(&mut self, tok: &mut Token) {
+            if let Token::Literal(ch) = tok {
+                *ch = ch.to_ascii_uppercase();
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_mut_rewrites() {
+        let mut tok = Token::Disjunction(vec![
+            Token::Conjunction(vec![Token::Literal('a'), Token::Digit]),
+            Token::Capturing(Box::new(Token::Literal('b')), Some("x".to_owned())),
+        ]);
+
+        tok.walk_mut(&mut Uppercase);
+
+        assert_eq!(
+            tok,
+            Token::Disjunction(vec![
+                Token::Conjunction(vec![Token::Literal('A'), Token::Digit]),
+                Token::Capturing(Box::new(Token::Literal('B')), Some("x".to_owned())),
+            ])
+        );
+    }
+
+    fn round_trip(pattern: &str) -> String {
+        let tok = parse_expr(pattern.chars()).expect("parse");
+        let printed = tok.to_string();
+
+        assert_eq!(parse_expr(printed.chars()).expect("reparse"), tok);
+        printed
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        assert_eq!(round_trip("hello"), "hello");
+        assert_eq!(round_trip("a|b(?:c|d)e"), "a|b(?:c|d)e");
+        assert_eq!(
+            round_trip("(?<year>\\d{4})-(\\d{1,2})"),
+            "(?<year>\\d{4})-(\\d{1,2})"
+        );
+        assert_eq!(round_trip("^[a-z_\\]-]+?\\.$"), "^[a-z_\\]\\-]+?\\.$");
+        assert_eq!(round_trip("(?:ab)*x{2,}y{0,1}?"), "(?:ab)*x{2,}y{0,1}?");
+        assert_eq!(round_trip("\\?\\^\\$\\t"), "\\?\\^\\$\\t");
+        assert_eq!(round_trip("[\\u{0}-\\u{10FFFF}]é"), "[\\u{0}-\\u{10ffff}]é");
+    }
+
+    #[test]
+    fn test_display_wraps_alternation() {
+        let tok = Token::GreedyQuantifier(
+            Box::new(Token::Disjunction(vec![
+                Token::Literal('a'),
+                Token::Conjunction(vec![Token::Literal('b'), Token::Literal('c')]),
+            ])),
+            1,
+            None,
+        );
+
+        assert_eq!(tok.to_string(), "(?:a|bc)+");
+    }
+}
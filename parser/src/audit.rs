@@ -0,0 +1,500 @@
+//! Detection of expressions prone to catastrophic backtracking.
+//!
+//! Backtracking engines try every way an expression can match a string
+//! before giving up on it. When a quantified token can split the same input
+//! in many ways, a rejected string of a few dozen characters is enough to
+//! keep them busy for years. Whether two parts of an expression can match
+//! the same input is decided on their compiled automata, so the findings do
+//! not depend on how the parts are spelled.
+//!
+//! Engines search their input rather than match all of it, and stop at the
+//! first match found. An attack a search matches a prefix of right away,
+//! like any string starting with `a` for the unanchored `(a+)+`, only slows
+//! down matching the whole input, and the finding is rated low.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::{Span, Token},
+    compiler::{self, Dfa},
+    generate::size,
+};
+
+/// Repetitions of the pumped string in attacks on exponential findings.
+const EXPONENTIAL_REPEAT: usize = 30;
+/// Repetitions of the pumped string in attacks on polynomial findings.
+const POLYNOMIAL_REPEAT: usize = 10_000;
+
+/// Characters tried, in order, to make an attack string fail to match.
+const SUFFIXES: [char; 7] = ['!', ' ', '\n', '\0', 'a', '0', '_'];
+
+/// How bad a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The blow-up is limited by the bound of a quantifier, or a search
+    /// finds a match before it starts.
+    Low,
+    /// Matching time grows polynomially with the input length.
+    Medium,
+    /// Matching time grows exponentially with the input length.
+    High,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// The construct responsible for a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Risk {
+    /// A quantified token whose repetitions can themselves be split in
+    /// several ways, as in `(a+)+`.
+    NestedQuantifiers,
+    /// Alternatives under a quantifier matching the same input, as in
+    /// `(a|a)*` or `(a|aa)+`.
+    OverlappingAlternatives,
+    /// Two quantified tokens in a row matching the same input, as in
+    /// `\d+\d+`.
+    AdjacentQuantifiers,
+}
+
+impl Display for Risk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Risk::NestedQuantifiers => write!(f, "nested quantifiers"),
+            Risk::OverlappingAlternatives => write!(f, "overlapping alternatives"),
+            Risk::AdjacentQuantifiers => write!(f, "adjacent overlapping quantifiers"),
+        }
+    }
+}
+
+/// A string triggering the backtracking behind a finding: `prefix`, then
+/// `pump` repeated `repeat` times, then `suffix` to make the match fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attack {
+    pub prefix: String,
+    pub pump: String,
+    pub repeat: usize,
+    pub suffix: String,
+}
+
+impl Attack {
+    pub fn build(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.prefix,
+            self.pump.repeat(self.repeat),
+            self.suffix
+        )
+    }
+}
+
+impl Display for Attack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.prefix.is_empty() {
+            write!(f, "{:?} + ", self.prefix)?;
+        }
+
+        write!(f, "{:?} × {}", self.pump, self.repeat)?;
+
+        if !self.suffix.is_empty() {
+            write!(f, " + {:?}", self.suffix)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A part of an expression prone to catastrophic backtracking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub risk: Risk,
+    pub severity: Severity,
+    /// The quantified token, or the first of two adjacent ones.
+    pub token: Token,
+    /// Where the construct is in the source, when spans are known.
+    pub span: Option<Span>,
+    pub attack: Attack,
+}
+
+/// Looks for constructs prone to catastrophic backtracking in `tok`.
+/// `spans` are the source spans of its tokens in pre-order, as returned by
+/// [`crate::Regex::spans`], and may be empty.
+pub fn audit(tok: &Token, spans: &[Span]) -> Vec<Finding> {
+    let search = Token::Conjunction(vec![tok.clone(), anything(0)]);
+    let mut auditor = Auditor {
+        root: tok,
+        dfa: compiler::compile(tok),
+        search: compiler::compile(&search),
+        spans,
+        findings: vec![],
+    };
+
+    auditor.visit(tok, 0);
+    auditor.findings
+}
+
+struct Auditor<'a> {
+    root: &'a Token,
+    dfa: Dfa,
+    /// The strings with a prefix matched by the expression.
+    search: Dfa,
+    spans: &'a [Span],
+    findings: Vec<Finding>,
+}
+
+impl Auditor<'_> {
+    /// Audits the tree rooted at `tok`, numbered `id` in pre-order.
+    fn visit(&mut self, tok: &Token, id: usize) {
+        if let Token::GreedyQuantifier(child, _, max) | Token::LazyQuantifier(child, _, max) = tok {
+            self.quantifier(tok, id, child, *max);
+        }
+
+        if let Token::Conjunction(tokens) = tok {
+            self.sequence(tokens, id + 1);
+        }
+
+        let mut child_id = id + 1;
+
+        for child in tok.children() {
+            self.visit(child, child_id);
+            child_id += size(child);
+        }
+    }
+
+    /// Checks whether the repetitions of a quantifier can split the same
+    /// input in several ways.
+    fn quantifier(&mut self, tok: &Token, id: usize, child: &Token, max: Option<usize>) {
+        let (severity, repeat) = match max {
+            None => (Severity::High, EXPONENTIAL_REPEAT),
+            Some(max) if max > 1 => (Severity::Low, max),
+            Some(_) => return,
+        };
+
+        let mut risk = Risk::OverlappingAlternatives;
+        let mut pump = None;
+
+        match unwrap(child) {
+            // A class matches a single character whichever item matches it.
+            inner if inner.is_class() => {}
+            Token::Disjunction(alternatives) => {
+                let alternatives: Vec<Dfa> = alternatives.iter().map(non_empty).collect();
+
+                pump = alternatives.iter().enumerate().find_map(|(i, a)| {
+                    alternatives[i + 1..]
+                        .iter()
+                        .find_map(|b| compiler::intersect(a, b).shortest_accepted())
+                });
+            }
+            _ => {}
+        }
+
+        if pump.is_none() {
+            let twice = Token::Conjunction(vec![child.clone(), child.clone()]);
+
+            pump = compiler::intersect(&non_empty(child), &compiler::compile(&twice))
+                .shortest_accepted();
+
+            if has_quantifier(child) {
+                risk = Risk::NestedQuantifiers;
+            }
+        }
+
+        if let Some(pump) = pump {
+            self.report(risk, (severity, repeat), tok, id, self.span(id), pump);
+        }
+    }
+
+    /// Checks whether two quantified tokens in a row can share their input.
+    fn sequence(&mut self, tokens: &[Token], first_id: usize) {
+        let mut id = first_id;
+
+        for pair in tokens.windows(2) {
+            let next_id = id + size(&pair[0]);
+
+            if let (Some(first), Some(second)) = (unbounded(&pair[0]), unbounded(&pair[1])) {
+                let pump =
+                    compiler::intersect(&non_empty(first), &non_empty(second)).shortest_accepted();
+                let span = self
+                    .span(id)
+                    .zip(self.span(next_id))
+                    .map(|(first, second)| Span::new(first.start, second.end));
+
+                if let Some(pump) = pump {
+                    self.report(
+                        Risk::AdjacentQuantifiers,
+                        (Severity::Medium, POLYNOMIAL_REPEAT),
+                        &pair[0],
+                        id,
+                        span,
+                        pump,
+                    );
+                }
+            }
+
+            id = next_id;
+        }
+    }
+
+    fn span(&self, id: usize) -> Option<Span> {
+        self.spans.get(id).copied()
+    }
+
+    /// Records a finding with an attack repeating `pump` as many times as
+    /// given with the severity, unless no string built around it fails to
+    /// match, in which case a backtracking engine never has to give up. When
+    /// a search matches a prefix of every such string, the finding is rated
+    /// low.
+    fn report(
+        &mut self,
+        risk: Risk,
+        (severity, repeat): (Severity, usize),
+        tok: &Token,
+        id: usize,
+        span: Option<Span>,
+        pump: String,
+    ) {
+        let mut prefix = String::new();
+
+        write_prefix(self.root, &mut 0, id, &mut prefix);
+
+        let attack = |repeat, suffix: &char| Attack {
+            prefix: prefix.clone(),
+            pump: pump.clone(),
+            repeat,
+            suffix: suffix.to_string(),
+        };
+        let searched = SUFFIXES
+            .iter()
+            .map(|suffix| attack(repeat, suffix))
+            .find(|attack| !self.search.accepts(&attack.build()));
+        let matched = || {
+            SUFFIXES
+                .iter()
+                .map(|suffix| attack(repeat, suffix))
+                .find(|attack| !self.dfa.accepts(&attack.build()))
+        };
+        let found = match searched {
+            Some(attack) => Some((severity, attack)),
+            None => matched().map(|attack| (Severity::Low, attack)),
+        };
+
+        if let Some((severity, attack)) = found {
+            self.findings.push(Finding {
+                risk,
+                severity,
+                token: tok.clone(),
+                span,
+                attack,
+            });
+        }
+    }
+}
+
+/// The token a group or a single-token sequence stands for.
+fn unwrap(tok: &Token) -> &Token {
+    match tok {
+        Token::Capturing(child, _) => unwrap(child),
+        Token::Conjunction(tokens) if tokens.len() == 1 => unwrap(&tokens[0]),
+        _ => tok,
+    }
+}
+
+/// The repeated token of a quantifier without an upper bound.
+fn unbounded(tok: &Token) -> Option<&Token> {
+    match unwrap(tok) {
+        Token::GreedyQuantifier(child, _, None) | Token::LazyQuantifier(child, _, None) => {
+            Some(child)
+        }
+        _ => None,
+    }
+}
+
+fn has_quantifier(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::GreedyQuantifier(_, _, _) | Token::LazyQuantifier(_, _, _)
+    ) || tok.children().iter().any(has_quantifier)
+}
+
+/// Any string of at least `min` characters.
+fn anything(min: usize) -> Token {
+    let any = Token::Disjunction(vec![Token::Whitespace, Token::NotWhitespace]);
+
+    Token::GreedyQuantifier(Box::new(any), min, None)
+}
+
+/// The non-empty strings matched by `tok`.
+fn non_empty(tok: &Token) -> Dfa {
+    compiler::intersect(&compiler::compile(tok), &compiler::compile(&anything(1)))
+}
+
+/// Appends to `out` a short string matched by the tokens of the tree rooted
+/// at `tok` that come before the token numbered `target`, and returns
+/// whether `target` was reached.
+fn write_prefix(tok: &Token, id: &mut usize, target: usize, out: &mut String) -> bool {
+    let current = *id;
+    let end = current + size(tok);
+
+    *id = end;
+
+    if current == target {
+        return true;
+    }
+
+    if !(current..end).contains(&target) {
+        out.push_str(
+            &compiler::compile(tok)
+                .shortest_accepted()
+                .unwrap_or_default(),
+        );
+        return false;
+    }
+
+    let mut child_id = current + 1;
+
+    match tok {
+        Token::Disjunction(tokens) => {
+            for child in tokens {
+                if (child_id..child_id + size(child)).contains(&target) {
+                    return write_prefix(child, &mut { child_id }, target, out);
+                }
+                child_id += size(child);
+            }
+            false
+        }
+        Token::GreedyQuantifier(child, min, _) | Token::LazyQuantifier(child, min, _) => {
+            let shortest = compiler::compile(child)
+                .shortest_accepted()
+                .unwrap_or_default();
+
+            for _ in 1..*min {
+                out.push_str(&shortest);
+            }
+            write_prefix(child, &mut child_id, target, out)
+        }
+        _ => tok
+            .children()
+            .iter()
+            .any(|child| write_prefix(child, &mut child_id, target, out)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr_with_spans;
+
+    fn audit_pattern(pattern: &str) -> Vec<Finding> {
+        let (tok, spans) = parse_expr_with_spans(pattern.chars()).expect("parse");
+
+        audit(&tok, &spans)
+    }
+
+    fn risks(pattern: &str) -> Vec<(Risk, Severity)> {
+        audit_pattern(pattern)
+            .into_iter()
+            .map(|finding| (finding.risk, finding.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_quantifiers() {
+        let findings = audit_pattern("^x(a+)+$");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].risk, Risk::NestedQuantifiers);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].span, Some(Span::new(2, 7)));
+        assert_eq!(
+            findings[0].attack,
+            Attack {
+                prefix: "x".to_owned(),
+                pump: "aa".to_owned(),
+                repeat: EXPONENTIAL_REPEAT,
+                suffix: "!".to_owned(),
+            }
+        );
+        assert_eq!(
+            findings[0].attack.to_string(),
+            "\"x\" + \"aa\" × 30 + \"!\""
+        );
+
+        assert_eq!(
+            risks(r"^(\w+\s?)*$"),
+            vec![(Risk::NestedQuantifiers, Severity::High)]
+        );
+        assert_eq!(
+            risks("(a*){2,5}"),
+            vec![(Risk::NestedQuantifiers, Severity::Low)]
+        );
+        assert_eq!(risks("(a+b)+"), vec![]);
+    }
+
+    #[test]
+    fn test_bounded_attack() {
+        let findings = audit_pattern("^(a*){2,5}$");
+
+        assert_eq!(findings[0].severity, Severity::Low);
+        assert_eq!(findings[0].attack.repeat, 5);
+    }
+
+    #[test]
+    fn test_search() {
+        // A search matches the a's at once, only a full match backtracks.
+        assert_eq!(
+            risks("(a+)+"),
+            vec![(Risk::NestedQuantifiers, Severity::Low)]
+        );
+        assert_eq!(
+            risks("(a+)+$"),
+            vec![(Risk::NestedQuantifiers, Severity::High)]
+        );
+        assert_eq!(
+            risks("(a+)+b"),
+            vec![(Risk::NestedQuantifiers, Severity::High)]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_alternatives() {
+        let findings = audit_pattern(r"(?:\d|[0-5])*x");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].risk, Risk::OverlappingAlternatives);
+        assert_eq!(findings[0].attack.pump, "0");
+
+        assert_eq!(
+            risks("^(a|aa)+$"),
+            vec![(Risk::OverlappingAlternatives, Severity::High)]
+        );
+        assert_eq!(risks("(a|b)*"), vec![]);
+        assert_eq!(risks("(a|ab)*"), vec![]);
+        assert_eq!(risks(r"([a\w])*"), vec![]);
+    }
+
+    #[test]
+    fn test_adjacent_quantifiers() {
+        let findings = audit_pattern(r"id=\d+\d*;");
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].risk, Risk::AdjacentQuantifiers);
+        assert_eq!(findings[0].severity, Severity::Medium);
+        assert_eq!(findings[0].span, Some(Span::new(3, 9)));
+        assert_eq!(findings[0].attack.prefix, "id=");
+
+        assert_eq!(risks(r"\d+[a-z]+"), vec![]);
+    }
+
+    #[test]
+    fn test_unexploitable() {
+        // Every string matches, so the engine never backtracks for long.
+        assert_eq!(risks(r"(a+)+[\s\S]*"), vec![]);
+    }
+}
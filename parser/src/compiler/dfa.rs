@@ -0,0 +1,334 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{
+    nfa::{Assertion, Edge, Nfa},
+    ranges::{self, Ranges},
+};
+
+/// A complete deterministic automaton.
+///
+/// The character space is split into `classes`, contiguous ranges every
+/// character of which behaves the same in every state, and each state has
+/// exactly one transition per class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    classes: Ranges,
+    transitions: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+    start: usize,
+}
+
+/// What precedes the current position, which decides whether the `^` and
+/// `\b` assertions hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Previous {
+    Start,
+    Word,
+    Other,
+}
+
+/// What follows the current position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Next {
+    Char(bool),
+    End,
+}
+
+fn holds(assertion: Assertion, previous: Previous, next: Next) -> bool {
+    match assertion {
+        Assertion::Start => previous == Previous::Start,
+        Assertion::End => next == Next::End,
+        Assertion::WordBoundary => (previous == Previous::Word) != (next == Next::Char(true)),
+    }
+}
+
+/// The states reachable from `states` without consuming input.
+fn closure(nfa: &Nfa, states: &[usize], previous: Previous, next: Next) -> Vec<bool> {
+    let mut reached = vec![false; nfa.states().len()];
+    let mut stack = states.to_vec();
+
+    while let Some(state) = stack.pop() {
+        if std::mem::replace(&mut reached[state], true) {
+            continue;
+        }
+
+        for (edge, to) in &nfa.states()[state].transitions {
+            match edge {
+                Edge::Epsilon => stack.push(*to),
+                Edge::Assertion(assertion) if holds(*assertion, previous, next) => stack.push(*to),
+                _ => {}
+            }
+        }
+    }
+
+    reached
+}
+
+impl Dfa {
+    /// Builds the automaton with the subset construction. A state is the set
+    /// of NFA states reached after consuming a character, not yet closed
+    /// under assertions, along with the kind of character consumed.
+    pub fn new(nfa: &Nfa) -> Self {
+        let classes = ranges::partition(
+            nfa.states()
+                .iter()
+                .flat_map(|state| state.transitions.iter())
+                .filter_map(|(edge, _)| match edge {
+                    Edge::Ranges(ranges) => Some(ranges.as_slice()),
+                    _ => None,
+                })
+                .chain([ranges::word_ranges().as_slice()]),
+        );
+
+        let mut dfa = Self {
+            classes,
+            transitions: vec![],
+            accepting: vec![],
+            start: 0,
+        };
+        let mut ids: HashMap<(Vec<usize>, Previous), usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let start = (vec![nfa.start()], Previous::Start);
+
+        ids.insert(start.clone(), 0);
+        queue.push_back(start);
+
+        while let Some((kernel, previous)) = queue.pop_front() {
+            let accepting = closure(nfa, &kernel, previous, Next::End)[nfa.accept()];
+            let mut transitions = vec![];
+
+            for class in &dfa.classes {
+                let ch = class.0;
+                let reached = closure(nfa, &kernel, previous, Next::Char(ranges::is_word(ch)));
+                let mut targets: Vec<usize> = reached
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, reached)| **reached)
+                    .flat_map(|(state, _)| nfa.states()[state].transitions.iter())
+                    .filter_map(|(edge, to)| match edge {
+                        Edge::Ranges(ranges) if ranges::contains(ranges, ch) => Some(*to),
+                        _ => None,
+                    })
+                    .collect();
+
+                targets.sort();
+                targets.dedup();
+
+                let previous = match ranges::is_word(ch) {
+                    true => Previous::Word,
+                    false => Previous::Other,
+                };
+                let key = (targets, previous);
+                let next_id = ids.len();
+                let id = *ids.entry(key.clone()).or_insert_with(|| {
+                    queue.push_back(key);
+                    next_id
+                });
+
+                transitions.push(id);
+            }
+
+            dfa.transitions.push(transitions);
+            dfa.accepting.push(accepting);
+        }
+
+        dfa
+    }
+
+    /// The equivalent automaton with the fewest states, numbered in
+    /// breadth-first order from the start state.
+    pub fn minimize(&self) -> Self {
+        let mut blocks: Vec<usize> = self.accepting.iter().map(|a| *a as usize).collect();
+        let mut count = 0;
+
+        loop {
+            let mut ids: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+            let refined: Vec<usize> = (0..self.state_count())
+                .map(|state| {
+                    let signature = self.transitions[state]
+                        .iter()
+                        .map(|to| blocks[*to])
+                        .collect();
+                    let next_id = ids.len();
+                    *ids.entry((blocks[state], signature)).or_insert(next_id)
+                })
+                .collect();
+
+            blocks = refined;
+
+            if ids.len() == count {
+                break;
+            }
+            count = ids.len();
+        }
+
+        let mut order = vec![None; count];
+        let mut representatives = vec![];
+        let mut queue = VecDeque::from([self.start]);
+
+        order[blocks[self.start]] = Some(0);
+
+        while let Some(state) = queue.pop_front() {
+            representatives.push(state);
+
+            for to in &self.transitions[state] {
+                if order[blocks[*to]].is_none() {
+                    order[blocks[*to]] = Some(representatives.len() + queue.len());
+                    queue.push_back(*to);
+                }
+            }
+        }
+
+        let renumber = |state: usize| order[blocks[state]].expect("reachable state");
+
+        Self {
+            classes: self.classes.clone(),
+            transitions: representatives
+                .iter()
+                .map(|state| {
+                    self.transitions[*state]
+                        .iter()
+                        .map(|to| renumber(*to))
+                        .collect()
+                })
+                .collect(),
+            accepting: representatives
+                .iter()
+                .map(|state| self.accepting[*state])
+                .collect(),
+            start: 0,
+        }
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    /// Whether no string at all is accepted.
+    pub fn is_empty(&self) -> bool {
+        self.shortest_accepted().is_none()
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn classes(&self) -> &[(char, char)] {
+        &self.classes
+    }
+
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.accepting[state]
+    }
+
+    /// The state reached from `state` on a character of `class`.
+    pub fn next(&self, state: usize, class: usize) -> usize {
+        self.transitions[state][class]
+    }
+
+    pub fn step(&self, state: usize, ch: char) -> usize {
+        self.next(state, ranges::find(&self.classes, ch))
+    }
+
+    /// Whether each state can still reach an accepting state.
+    pub fn live_states(&self) -> Vec<bool> {
+        let mut alive: Vec<bool> = self.accepting.clone();
+
+        loop {
+            let mut changed = false;
+
+            for state in 0..self.state_count() {
+                if !alive[state] && self.transitions[state].iter().any(|to| alive[*to]) {
+                    alive[state] = true;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return alive;
+            }
+        }
+    }
+
+    /// Whether the expression matches the whole of `input`.
+    pub fn accepts(&self, input: &str) -> bool {
+        self.accepting[input
+            .chars()
+            .fold(self.start, |state, ch| self.step(state, ch))]
+    }
+
+    /// The shortest accepted string, if any. Ties are broken by class order.
+    pub fn shortest_accepted(&self) -> Option<String> {
+        let mut parents: Vec<Option<(usize, char)>> = vec![None; self.state_count()];
+        let mut seen = vec![false; self.state_count()];
+        let mut queue = VecDeque::from([self.start]);
+
+        seen[self.start] = true;
+
+        while let Some(state) = queue.pop_front() {
+            if self.accepting[state] {
+                let mut input = vec![];
+                let mut at = state;
+
+                while let Some((parent, ch)) = parents[at] {
+                    input.push(ch);
+                    at = parent;
+                }
+
+                return Some(input.into_iter().rev().collect());
+            }
+
+            for (class, to) in self.transitions[state].iter().enumerate() {
+                if !std::mem::replace(&mut seen[*to], true) {
+                    parents[*to] = Some((state, ranges::representative(self.classes[class])));
+                    queue.push_back(*to);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The automaton accepting exactly the strings `self` rejects.
+    pub fn complement(&self) -> Dfa {
+        Dfa {
+            accepting: self.accepting.iter().map(|accepting| !accepting).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Runs `self` and `other` in lockstep; a state of the result is
+    /// accepting when `combine` holds for the pair of states it stands for.
+    pub fn product(&self, other: &Dfa, combine: impl Fn(bool, bool) -> bool) -> Dfa {
+        let classes = ranges::partition([self.classes.as_slice(), other.classes.as_slice()]);
+        let mut ids = HashMap::from([((self.start, other.start), 0)]);
+        let mut queue = VecDeque::from([(self.start, other.start)]);
+        let mut dfa = Dfa {
+            classes,
+            transitions: vec![],
+            accepting: vec![],
+            start: 0,
+        };
+
+        while let Some((left, right)) = queue.pop_front() {
+            let transitions = dfa
+                .classes
+                .iter()
+                .map(|(ch, _)| {
+                    let pair = (self.step(left, *ch), other.step(right, *ch));
+                    let next_id = ids.len();
+                    *ids.entry(pair).or_insert_with(|| {
+                        queue.push_back(pair);
+                        next_id
+                    })
+                })
+                .collect();
+
+            dfa.transitions.push(transitions);
+            dfa.accepting
+                .push(combine(self.accepting[left], other.accepting[right]));
+        }
+
+        dfa
+    }
+}
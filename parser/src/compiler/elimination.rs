@@ -0,0 +1,137 @@
+use crate::{ast::Token, simplify::simplify};
+
+use super::{
+    ranges::{self, Ranges},
+    Dfa,
+};
+
+/// The simplest token matching exactly one character of `ranges`.
+pub(crate) fn class_token(ranges: Ranges) -> Token {
+    let ranges = ranges::normalize(ranges);
+    let named = [
+        Token::Any,
+        Token::Alphanumeric,
+        Token::Digit,
+        Token::Whitespace,
+        Token::NotAlphanumeric,
+        Token::NotDigit,
+        Token::NotWhitespace,
+    ];
+
+    if ranges == [('\0', char::MAX)] {
+        return Token::Disjunction(vec![Token::Whitespace, Token::NotWhitespace]);
+    }
+
+    if let Some(tok) = named
+        .into_iter()
+        .find(|tok| ranges::token_ranges(tok).as_ref() == Some(&ranges))
+    {
+        return tok;
+    }
+
+    match ranges.as_slice() {
+        [(lo, hi)] if lo == hi => Token::Literal(*lo),
+        _ => Token::Disjunction(
+            ranges
+                .into_iter()
+                .flat_map(|(lo, hi)| match lo == hi {
+                    true => vec![Token::Literal(lo)],
+                    false => vec![Token::AsciiRange(lo, hi)],
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn union(a: Option<Token>, b: Option<Token>) -> Option<Token> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(Token::Disjunction(vec![a, b])),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Converts `dfa` back to an expression by state elimination.
+///
+/// States that cannot reach an accepting state are dropped first, and the
+/// state with the fewest edges is eliminated at each step to keep the
+/// result small.
+pub fn to_token(dfa: &Dfa) -> Token {
+    let count = dfa.state_count();
+    let alive = dfa.live_states();
+
+    if !alive[dfa.start()] {
+        return Token::Disjunction(vec![]);
+    }
+
+    // States 0..count are the automaton's, `count` is a new start state and
+    // `count + 1` a new accepting state.
+    let size = count + 2;
+    let mut edges: Vec<Vec<Option<Token>>> = vec![vec![None; size]; size];
+
+    for from in (0..count).filter(|state| alive[*state]) {
+        let mut labels: Vec<Ranges> = vec![vec![]; count];
+
+        for (class, range) in dfa.classes().iter().enumerate() {
+            labels[dfa.next(from, class)].push(*range);
+        }
+
+        for (to, label) in labels.into_iter().enumerate() {
+            if alive[to] && !label.is_empty() {
+                edges[from][to] = Some(class_token(label));
+            }
+        }
+
+        if dfa.is_accepting(from) {
+            edges[from][count + 1] = Some(Token::Conjunction(vec![]));
+        }
+    }
+
+    edges[count][dfa.start()] = Some(Token::Conjunction(vec![]));
+
+    let mut remaining: Vec<usize> = (0..count).filter(|state| alive[*state]).collect();
+
+    while !remaining.is_empty() {
+        let degree = |state: usize| {
+            (0..size)
+                .filter(|other| edges[*other][state].is_some() || edges[state][*other].is_some())
+                .count()
+        };
+        let (index, state) = remaining
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|(_, state)| degree(*state))
+            .unwrap();
+
+        remaining.swap_remove(index);
+
+        let looping = edges[state][state]
+            .take()
+            .map(|tok| Token::GreedyQuantifier(Box::new(tok), 0, None));
+        let incoming: Vec<(usize, Token)> = (0..size)
+            .filter_map(|from| edges[from][state].take().map(|tok| (from, tok)))
+            .collect();
+        let outgoing: Vec<(usize, Token)> = (0..size)
+            .filter_map(|to| edges[state][to].take().map(|tok| (to, tok)))
+            .collect();
+
+        for (from, before) in &incoming {
+            for (to, after) in &outgoing {
+                let path = Token::Conjunction(
+                    [Some(before.clone()), looping.clone(), Some(after.clone())]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                );
+
+                edges[*from][*to] = union(edges[*from][*to].take(), Some(path));
+            }
+        }
+    }
+
+    simplify(
+        edges[count][count + 1]
+            .take()
+            .unwrap_or(Token::Disjunction(vec![])),
+    )
+}
@@ -1,42 +1,244 @@
-use crate::parser::Token;
+//! Compilation of expressions to finite automata.
+//!
+//! The language of an expression is the set of strings it matches entirely,
+//! as if it were surrounded by `^` and `$`. Assertions are taken into account
+//! and lazy quantifiers accept the same strings as greedy ones.
 
-pub struct Automaton<'a> {
-    states: Vec<State<'a>>,
-    start_state: Option<&'a State<'a>>,
+mod dfa;
+mod elimination;
+mod nfa;
+pub(crate) mod ranges;
+
+pub use dfa::Dfa;
+pub use nfa::{Assertion, Edge, Nfa, NfaState};
+
+use crate::ast::Token;
+
+/// Compiles `tok` to a minimal deterministic automaton.
+pub fn compile(tok: &Token) -> Dfa {
+    Dfa::new(&Nfa::new(tok)).minimize()
 }
 
-impl<'a> Automaton<'a> {
-    pub fn new() -> Self {
-        Self {
-            start_state: None,
-            states: vec![],
+impl Dfa {
+    /// Converts the automaton back to an expression accepting the same
+    /// strings.
+    pub fn to_token(&self) -> Token {
+        elimination::to_token(self)
+    }
+
+    /// The transitions between live states, see [`Dfa::live_states`], one
+    /// per pair of states, labeled with the character class followed.
+    pub fn edges(&self) -> Vec<(usize, Token, usize)> {
+        let alive = self.live_states();
+        let mut edges = vec![];
+
+        for from in (0..self.state_count()).filter(|state| alive[*state]) {
+            let mut labels: Vec<ranges::Ranges> = vec![vec![]; self.state_count()];
+
+            for (class, range) in self.classes().iter().enumerate() {
+                labels[self.next(from, class)].push(*range);
+            }
+
+            for (to, label) in labels.into_iter().enumerate() {
+                if alive[to] && !label.is_empty() {
+                    edges.push((from, elimination::class_token(label), to));
+                }
+            }
         }
+
+        edges
+    }
+
+    /// The live states of the automaton as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        crate::rendering::dot::dfa(self)
+    }
+
+    /// The live states of the automaton as a Mermaid state diagram.
+    pub fn to_mermaid(&self) -> String {
+        crate::rendering::mermaid::dfa(self)
     }
-}
 
-enum Input {
-    Char(char),
-    Start,
-    End,
+    /// The live states of the automaton as a PlantUML state diagram.
+    pub fn to_plantuml(&self) -> String {
+        crate::rendering::plantuml::dfa(self)
+    }
 }
 
-struct State<'a> {
-    transitions: Vec<Box<dyn Fn(&Input) -> Option<&'a State<'a>> + 'a>>,
+impl Nfa {
+    /// The automaton as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        crate::rendering::dot::nfa(self)
+    }
+
+    /// The automaton as a Mermaid state diagram.
+    pub fn to_mermaid(&self) -> String {
+        crate::rendering::mermaid::nfa(self)
+    }
+
+    /// The automaton as a PlantUML state diagram.
+    pub fn to_plantuml(&self) -> String {
+        crate::rendering::plantuml::nfa(self)
+    }
 }
 
-impl<'a> State<'a> {
-    pub fn next(&'a self, input: &Input) -> Option<&'a State> {
-        todo!()
+impl Edge {
+    /// How the edge is written in listings and graphs: `ε`, an assertion or
+    /// a character class.
+    pub fn label(&self) -> String {
+        match self {
+            Edge::Epsilon => "ε".to_owned(),
+            Edge::Assertion(Assertion::Start) => Token::Start.to_string(),
+            Edge::Assertion(Assertion::End) => Token::End.to_string(),
+            Edge::Assertion(Assertion::WordBoundary) => Token::WordBoundary.to_string(),
+            Edge::Ranges(ranges) => elimination::class_token(ranges.clone()).to_string(),
+        }
     }
 }
 
+/// Whether every string accepted by `a` is also accepted by `b`.
+pub fn is_subset(a: &Dfa, b: &Dfa) -> bool {
+    a.product(b, |a, b| a && !b).is_empty()
+}
+
+/// The strings accepted by both `a` and `b`.
+pub fn intersect(a: &Dfa, b: &Dfa) -> Dfa {
+    a.product(b, |a, b| a && b).minimize()
+}
+
+/// The strings accepted by `a` but not by `b`.
+pub fn difference(a: &Dfa, b: &Dfa) -> Dfa {
+    a.product(b, |a, b| a && !b).minimize()
+}
+
+/// The strings not accepted by `a`.
+pub fn complement(a: &Dfa) -> Dfa {
+    a.complement()
+}
+
+/// A string accepted by only one of two automata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub input: String,
+    /// Whether the first automaton is the one accepting `input`.
+    pub accepted_by_first: bool,
+}
+
+/// Decides whether `a` and `b` accept the same language. When they do not,
+/// returns the shortest string accepted by exactly one of them.
+pub fn counterexample(a: &Dfa, b: &Dfa) -> Option<Counterexample> {
+    let input = a.product(b, |a, b| a != b).shortest_accepted()?;
+
+    Some(Counterexample {
+        accepted_by_first: a.accepts(&input),
+        input,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Automaton;
+    use super::*;
+    use crate::parser::parse_expr;
+
+    fn dfa(pattern: &str) -> Dfa {
+        compile(&parse_expr(pattern.chars()).expect("parse"))
+    }
+
+    #[test]
+    fn test_accepts() {
+        let phone = dfa(r"^(\+\d{1,2}\s)?\(?\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}$");
+
+        assert!(phone.accepts("555-123-4567"));
+        assert!(phone.accepts("+1 (555) 123-4567"));
+        assert!(!phone.accepts("555-123-456"));
+        assert!(!phone.accepts("555-123-4567 "));
+    }
+
+    #[test]
+    fn test_assertions() {
+        assert!(dfa(r"a\b b").accepts("a b"));
+        assert!(!dfa(r"a\bb").accepts("ab"));
+        assert!(dfa(r"\ba\b").accepts("a"));
+        assert!(!dfa(r"a^b").accepts("ab"));
+        assert!(!dfa(r"a$b").accepts("ab"));
+        assert!(dfa(r"^a*$").accepts(""));
+    }
+
+    #[test]
+    fn test_minimal() {
+        assert_eq!(dfa("a*").state_count(), 2);
+        assert_eq!(dfa("(?:a|a)*a*").state_count(), 2);
+        assert_eq!(dfa("[a-c]x|[b-d]x").state_count(), 4);
+    }
+
+    #[test]
+    fn test_equivalent() {
+        assert_eq!(counterexample(&dfa("abc|abd"), &dfa("ab[cd]")), None);
+        assert_eq!(counterexample(&dfa("(?:a+)+"), &dfa("a+?")), None);
+        assert_eq!(counterexample(&dfa(r"\d{2,3}"), &dfa(r"\d\d\d?")), None);
+    }
 
     #[test]
-    fn test() {
-        // let automaton = Automaton::new();
-        // automaton.start_state.unwrap().next(&Input::Start).unwrap()
+    fn test_counterexample() {
+        assert_eq!(
+            counterexample(&dfa("a*"), &dfa("a+")),
+            Some(Counterexample {
+                input: "".to_owned(),
+                accepted_by_first: true,
+            })
+        );
+        assert_eq!(
+            counterexample(&dfa(r"\d{2,3}"), &dfa(r"\d{2,4}")),
+            Some(Counterexample {
+                input: "0000".to_owned(),
+                accepted_by_first: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_subset() {
+        assert!(is_subset(&dfa("ab[cd]"), &dfa("[a-z]+")));
+        assert!(!is_subset(&dfa("[a-z]+"), &dfa("ab[cd]")));
+        assert!(is_subset(&dfa("[]"), &dfa("a")));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let words = dfa("[a-z]+");
+        let digits = dfa(r"\d+");
+        let mixed = dfa(r"[a-z0-9]+");
+
+        assert!(intersect(&words, &digits).is_empty());
+        assert!(difference(&words, &mixed).is_empty());
+        assert_eq!(
+            counterexample(&difference(&mixed, &words), &dfa(r"[a-z0-9]*\d[a-z0-9]*")),
+            None
+        );
+
+        let not_words = complement(&words);
+
+        assert!(not_words.accepts(""));
+        assert!(not_words.accepts("a1"));
+        assert!(!not_words.accepts("abc"));
+    }
+
+    fn round_trip(dfa: &Dfa) -> Token {
+        let tok = dfa.to_token();
+
+        assert_eq!(counterexample(dfa, &compile(&tok)), None, "{tok}");
+        tok
+    }
+
+    #[test]
+    fn test_to_token() {
+        assert_eq!(round_trip(&dfa("abc|abd")).to_string(), "ab[cd]");
+        assert_eq!(round_trip(&dfa("a+")).to_string(), "aa*");
+        assert_eq!(round_trip(&dfa("[]")).to_string(), "[]");
+        assert_eq!(round_trip(&complement(&dfa("[]"))).to_string(), "[\\s\\S]*");
+
+        round_trip(&dfa(r"^(\+\d{1,2}\s)?\(?\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}$"));
+        round_trip(&difference(&dfa("[a-z]+"), &dfa("a.*")));
+        round_trip(&complement(&dfa(r"\w+@\w+")));
     }
 }
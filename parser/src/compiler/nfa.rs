@@ -0,0 +1,143 @@
+use crate::ast::Token;
+
+use super::ranges::{self, Ranges};
+
+/// A zero-width condition on the position in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edge {
+    /// Followed without consuming input.
+    Epsilon,
+    /// Followed without consuming input when the assertion holds.
+    Assertion(Assertion),
+    /// Followed by consuming one character of the ranges.
+    Ranges(Ranges),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NfaState {
+    pub transitions: Vec<(Edge, usize)>,
+}
+
+/// A Thompson automaton with a single start and a single accepting state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nfa {
+    states: Vec<NfaState>,
+    start: usize,
+    accept: usize,
+}
+
+impl Nfa {
+    pub fn new(tok: &Token) -> Self {
+        let mut nfa = Self {
+            states: vec![NfaState::default()],
+            start: 0,
+            accept: 0,
+        };
+
+        nfa.accept = nfa.build(tok, 0);
+        nfa
+    }
+
+    pub fn states(&self) -> &[NfaState] {
+        &self.states
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn accept(&self) -> usize {
+        self.accept
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, edge: Edge, to: usize) {
+        self.states[from].transitions.push((edge, to));
+    }
+
+    /// Adds `edge` from `from` to a new state and returns it.
+    fn add_step(&mut self, from: usize, edge: Edge) -> usize {
+        let to = self.add_state();
+        self.add_edge(from, edge, to);
+        to
+    }
+
+    /// Builds `tok` starting at `from`, returning the state reached once it
+    /// matched. Loops only ever go back to states created here, so siblings
+    /// sharing `from` cannot leak into each other.
+    fn build(&mut self, tok: &Token, from: usize) -> usize {
+        if let Some(ranges) = ranges::token_ranges(tok) {
+            return self.add_step(from, Edge::Ranges(ranges));
+        }
+
+        match tok {
+            Token::Start => self.add_step(from, Edge::Assertion(Assertion::Start)),
+            Token::End => self.add_step(from, Edge::Assertion(Assertion::End)),
+            Token::WordBoundary => self.add_step(from, Edge::Assertion(Assertion::WordBoundary)),
+            Token::Capturing(tok, _) => self.build(tok, from),
+            Token::Conjunction(tokens) => tokens.iter().fold(from, |at, tok| self.build(tok, at)),
+            Token::Disjunction(tokens) => {
+                let end = self.add_state();
+
+                for tok in tokens {
+                    let entry = self.add_step(from, Edge::Epsilon);
+                    let exit = self.build(tok, entry);
+                    self.add_edge(exit, Edge::Epsilon, end);
+                }
+
+                end
+            }
+            Token::GreedyQuantifier(tok, min, max) | Token::LazyQuantifier(tok, min, max) => {
+                self.build_repetition(tok, *min, *max, from)
+            }
+            tok => unreachable!("{tok:?} is a single character token"),
+        }
+    }
+
+    fn build_repetition(
+        &mut self,
+        tok: &Token,
+        min: usize,
+        max: Option<usize>,
+        from: usize,
+    ) -> usize {
+        let mut at = from;
+
+        for _ in 0..min {
+            let entry = self.add_step(at, Edge::Epsilon);
+            at = self.build(tok, entry);
+        }
+
+        match max {
+            None => {
+                let entry = self.add_step(at, Edge::Epsilon);
+                let exit = self.build(tok, entry);
+                self.add_edge(exit, Edge::Epsilon, entry);
+                entry
+            }
+            Some(max) => {
+                let end = self.add_state();
+
+                for _ in min..max {
+                    self.add_edge(at, Edge::Epsilon, end);
+                    let entry = self.add_step(at, Edge::Epsilon);
+                    at = self.build(tok, entry);
+                }
+
+                self.add_edge(at, Edge::Epsilon, end);
+                end
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::ast::Token;
+
+/// Sorted, non-overlapping, inclusive character ranges.
+pub type Ranges = Vec<(char, char)>;
+
+const WORD: [(char, char); 4] = [('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const DIGIT: [(char, char); 1] = [('0', '9')];
+const WHITESPACE: [(char, char); 2] = [('\t', '\r'), (' ', ' ')];
+
+/// The character following `ch`, skipping the surrogate gap.
+pub fn next_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{D7FF}' => Some('\u{E000}'),
+        ch => char::from_u32(ch as u32 + 1),
+    }
+}
+
+/// The character preceding `ch`, skipping the surrogate gap.
+pub fn prev_char(ch: char) -> Option<char> {
+    match ch {
+        '\u{E000}' => Some('\u{D7FF}'),
+        ch => (ch as u32).checked_sub(1).and_then(char::from_u32),
+    }
+}
+
+pub fn is_word(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+pub fn word_ranges() -> Ranges {
+    WORD.to_vec()
+}
+
+/// Sorts and merges overlapping or touching ranges.
+pub fn normalize(mut ranges: Ranges) -> Ranges {
+    ranges.sort();
+
+    let mut merged: Ranges = vec![];
+
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last)) if next_char(*last).is_none_or(|next| next >= start) => {
+                *last = (*last).max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+/// Every character not in `ranges`.
+pub fn complement(ranges: &[(char, char)]) -> Ranges {
+    let mut result = vec![];
+    let mut start = Some('\0');
+
+    for (lo, hi) in normalize(ranges.to_vec()) {
+        if let (Some(from), Some(to)) = (start, prev_char(lo)) {
+            if from <= to {
+                result.push((from, to));
+            }
+        }
+        start = next_char(hi);
+    }
+
+    if let Some(from) = start {
+        result.push((from, char::MAX));
+    }
+
+    result
+}
+
+pub fn contains(ranges: &[(char, char)], ch: char) -> bool {
+    ranges.iter().any(|(lo, hi)| *lo <= ch && ch <= *hi)
+}
+
+/// The characters matched by a single-character token, `None` for tokens
+/// that do not consume exactly one character.
+pub fn token_ranges(tok: &Token) -> Option<Ranges> {
+    let ranges = match tok {
+        Token::Literal(ch) => vec![(*ch, *ch)],
+        Token::AsciiRange(start, end) => vec![(*start.min(end), *start.max(end))],
+        Token::Any => complement(&[('\n', '\n')]),
+        Token::Alphanumeric => WORD.to_vec(),
+        Token::Digit => DIGIT.to_vec(),
+        Token::Whitespace => WHITESPACE.to_vec(),
+        Token::NotAlphanumeric => complement(&WORD),
+        Token::NotDigit => complement(&DIGIT),
+        Token::NotWhitespace => complement(&WHITESPACE),
+        Token::Disjunction(tokens) if tok.is_class() => {
+            tokens.iter().filter_map(token_ranges).flatten().collect()
+        }
+        _ => return None,
+    };
+
+    Some(normalize(ranges))
+}
+
+/// Splits the whole character space into the coarsest partition in which
+/// every range of `sets` is a union of parts.
+pub fn partition<'a>(sets: impl IntoIterator<Item = &'a [(char, char)]>) -> Ranges {
+    let mut bounds = vec!['\0'];
+
+    for ranges in sets {
+        for (lo, hi) in ranges {
+            bounds.push(*lo);
+            bounds.extend(next_char(*hi));
+        }
+    }
+
+    bounds.sort();
+    bounds.dedup();
+
+    bounds
+        .iter()
+        .enumerate()
+        .map(|(i, lo)| {
+            let hi = bounds
+                .get(i + 1)
+                .and_then(|next| prev_char(*next))
+                .unwrap_or(char::MAX);
+            (*lo, hi)
+        })
+        .collect()
+}
+
+/// Index of the part of `partition` containing `ch`.
+pub fn find(partition: &[(char, char)], ch: char) -> usize {
+    partition.partition_point(|(lo, _)| *lo <= ch) - 1
+}
+
+/// A readable character of the range, used when building example strings.
+pub fn representative((lo, hi): (char, char)) -> char {
+    ['a', '0', 'A', ' ', '_', '-', '.']
+        .into_iter()
+        .find(|ch| lo <= *ch && *ch <= hi)
+        .or_else(|| {
+            (lo..=hi)
+                .take(256)
+                .find(|ch| !ch.is_control() && !ch.is_whitespace())
+        })
+        .unwrap_or(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(
+            complement(&[('b', 'c'), ('a', 'a')]),
+            vec![('\0', '`'), ('d', char::MAX)]
+        );
+        assert_eq!(complement(&[('\0', char::MAX)]), vec![]);
+    }
+
+    #[test]
+    fn test_partition() {
+        let parts = partition([&[('a', 'z')][..], &[('m', 'm'), ('x', char::MAX)][..]]);
+
+        assert_eq!(
+            parts,
+            vec![
+                ('\0', '`'),
+                ('a', 'l'),
+                ('m', 'm'),
+                ('n', 'w'),
+                ('x', 'z'),
+                ('{', char::MAX),
+            ]
+        );
+        assert_eq!(find(&parts, 'q'), 3);
+        assert_eq!(find(&parts, '\0'), 0);
+    }
+}
@@ -0,0 +1,367 @@
+//! Descriptions of expressions in plain English.
+//!
+//! Simple tokens are described in a single phrase, such as `1 or 2 digits,
+//! as many as possible`. Sequences, alternatives and repeated groups that do
+//! not fit on one line get a header followed by their parts, indented.
+
+use crate::ast::Token;
+
+/// A line of a description, indented by `depth` levels.
+type Line = (usize, String);
+
+/// Describes `tok` in plain English, indenting nested parts by two spaces.
+pub fn explain(tok: &Token) -> String {
+    let mut explainer = Explainer { groups: 0 };
+
+    explainer
+        .describe(tok)
+        .into_iter()
+        .map(|(depth, text)| format!("{}{text}", "  ".repeat(depth)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct Explainer {
+    /// Capturing groups numbered so far.
+    groups: usize,
+}
+
+impl Explainer {
+    fn describe(&mut self, tok: &Token) -> Vec<Line> {
+        let line = |text: &str| vec![(0, text.to_owned())];
+
+        match tok {
+            Token::Conjunction(tokens) => self.sequence(tokens),
+            Token::Disjunction(tokens) if tokens.is_empty() => line("nothing, this never matches"),
+            Token::Disjunction(_) if tok.is_class() => vec![(0, phrase(tok))],
+            Token::Disjunction(tokens) => self.alternatives(tokens),
+            Token::Capturing(child, name) => {
+                self.groups += 1;
+
+                let label = match name {
+                    Some(name) => format!("group {} {name:?}", self.groups),
+                    None => format!("group {}", self.groups),
+                };
+
+                match self.describe(child).as_slice() {
+                    [(_, text)] => vec![(0, format!("{text} ({label})"))],
+                    lines => block(format!("{label}:"), lines),
+                }
+            }
+            Token::GreedyQuantifier(child, min, max) => self.quantifier(child, *min, *max, false),
+            Token::LazyQuantifier(child, min, max) => self.quantifier(child, *min, *max, true),
+            Token::Literal(ch) => vec![(0, format!("{:?}", ch.to_string()))],
+            Token::Start => line("start of line"),
+            Token::End => line("end of line"),
+            Token::WordBoundary => line("a word boundary"),
+            Token::Any
+            | Token::Alphanumeric
+            | Token::Digit
+            | Token::Whitespace
+            | Token::NotAlphanumeric
+            | Token::NotDigit
+            | Token::NotWhitespace
+            | Token::AsciiRange(_, _) => vec![(0, phrase(tok))],
+        }
+    }
+
+    /// Describes the tokens one after the other, runs of literals as a
+    /// single string.
+    fn sequence(&mut self, tokens: &[Token]) -> Vec<Line> {
+        let mut lines = vec![];
+        let mut rest = tokens;
+
+        if tokens.is_empty() {
+            return vec![(0, "nothing, the empty string".to_owned())];
+        }
+
+        while let Some(first) = rest.first() {
+            let literals: String = rest
+                .iter()
+                .map_while(|tok| match tok {
+                    Token::Literal(ch) => Some(*ch),
+                    _ => None,
+                })
+                .collect();
+            let mut described = match literals.chars().count() {
+                0 | 1 => {
+                    rest = &rest[1..];
+                    self.describe(first)
+                }
+                count => {
+                    rest = &rest[count..];
+                    vec![(0, format!("{literals:?}"))]
+                }
+            };
+
+            if !lines.is_empty() {
+                described[0].1.insert_str(0, "then ");
+            }
+            lines.extend(described);
+        }
+
+        lines
+    }
+
+    fn alternatives(&mut self, tokens: &[Token]) -> Vec<Line> {
+        let described: Vec<Vec<Line>> = tokens.iter().map(|tok| self.describe(tok)).collect();
+
+        if described.iter().all(|lines| lines.len() == 1) {
+            let texts: Vec<&str> = described.iter().map(|lines| lines[0].1.as_str()).collect();
+
+            return vec![(0, format!("either {}", texts.join(", or ")))];
+        }
+
+        let mut lines = vec![];
+
+        for (i, alternative) in described.iter().enumerate() {
+            let header = match i {
+                0 => "either:",
+                _ => "or:",
+            };
+
+            lines.extend(block(header.to_owned(), alternative));
+        }
+
+        lines
+    }
+
+    fn quantifier(
+        &mut self,
+        child: &Token,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+    ) -> Vec<Line> {
+        if (min, max) == (1, Some(1)) {
+            return self.describe(child);
+        }
+
+        let greed = match (max == Some(min), lazy) {
+            (true, _) => "",
+            (false, false) => ", as many as possible",
+            (false, true) => ", as few as possible",
+        };
+
+        if (min, max) == (0, Some(1)) {
+            let greed = match lazy {
+                true => ", preferably not",
+                false => "",
+            };
+
+            return match (noun(child, false), self.describe(child).as_slice()) {
+                (Some(noun), _) => vec![(0, format!("optionally {noun}{greed}"))],
+                (None, [(_, text)]) => vec![(0, format!("optionally {text}{greed}"))],
+                (None, lines) => block(format!("optionally{greed}:"), lines),
+            };
+        }
+
+        if let (Some(noun), Some(count)) = (noun(child, true), count(min, max)) {
+            return vec![(0, format!("{count} {noun}{greed}"))];
+        }
+
+        let times = times(min, max);
+
+        match self.describe(child).as_slice() {
+            [(_, text)] => vec![(0, format!("{text}, {times}{greed}"))],
+            lines => block(format!("{times}{greed}:"), lines),
+        }
+    }
+}
+
+fn block(header: String, lines: &[Line]) -> Vec<Line> {
+    std::iter::once((0, header))
+        .chain(lines.iter().map(|(depth, text)| (depth + 1, text.clone())))
+        .collect()
+}
+
+/// How many times a quantifier repeats a token, before a noun. `None` for
+/// counts that read better with "times" after the token.
+fn count(min: usize, max: Option<usize>) -> Option<String> {
+    match (min, max) {
+        (0, None) => Some("zero or more".to_owned()),
+        (1, None) => Some("one or more".to_owned()),
+        (min, None) => Some(format!("{min} or more")),
+        (min, Some(max)) if min == max => Some(format!("exactly {min}")),
+        (min, Some(max)) if min + 1 == max => Some(format!("{min} or {max}")),
+        (min, Some(max)) if min < max => Some(format!("{min} to {max}")),
+        _ => None,
+    }
+}
+
+/// How many times a quantifier repeats a token, after the token.
+fn times(min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (1, Some(1)) => "exactly once".to_owned(),
+        (min, Some(max)) if min > max => format!("between {min} and {max} times, so never"),
+        (min, max) => format!("{} times", count(min, max).unwrap_or_default()),
+    }
+}
+
+/// The characters matched by a single-character token, as a noun. Literals
+/// have none, they read better quoted.
+fn noun(tok: &Token, plural: bool) -> Option<String> {
+    let pick = |one: &str, many: &str| Some(if plural { many } else { one }.to_owned());
+
+    match tok {
+        Token::Any => pick(
+            "any character but a newline",
+            "characters other than newlines",
+        ),
+        Token::Alphanumeric => pick("a word character", "word characters"),
+        Token::Digit => pick("a digit", "digits"),
+        Token::Whitespace => pick("a whitespace character", "whitespace characters"),
+        Token::NotAlphanumeric => pick("a non-word character", "non-word characters"),
+        Token::NotDigit => pick("a non-digit", "non-digits"),
+        Token::NotWhitespace => pick("a non-whitespace character", "non-whitespace characters"),
+        Token::AsciiRange(lo, hi) => {
+            let range = format!("from {:?} to {:?}", lo.to_string(), hi.to_string());
+            pick(
+                &format!("a character {range}"),
+                &format!("characters {range}"),
+            )
+        }
+        Token::Disjunction(tokens) if tok.is_class() => match tokens.as_slice() {
+            [] => None,
+            [Token::Literal(_)] => None,
+            [item] => noun(item, plural),
+            items => {
+                let items: Vec<String> = items.iter().map(class_item).collect();
+                let (last, rest) = items.split_last()?;
+                let list = format!("{} or {last}", rest.join(", "));
+
+                pick(
+                    &format!("one of {list}"),
+                    &format!("characters among {list}"),
+                )
+            }
+        },
+        _ => None,
+    }
+}
+
+/// A single-character token on its own: its noun, or the quoted character
+/// for literals and classes of a single one.
+fn phrase(tok: &Token) -> String {
+    match (noun(tok, false), tok) {
+        (Some(noun), _) => noun,
+        (None, Token::Disjunction(items)) if items.len() == 1 => phrase(&items[0]),
+        (None, Token::Literal(ch)) => format!("{:?}", ch.to_string()),
+        (None, tok) => format!("{:?}", tok.to_string()),
+    }
+}
+
+/// An item of a class, as listed after "one of".
+fn class_item(tok: &Token) -> String {
+    match tok {
+        Token::Literal(ch) => format!("{:?}", ch.to_string()),
+        Token::AsciiRange(lo, hi) => format!("{:?} to {:?}", lo.to_string(), hi.to_string()),
+        _ => noun(tok, false).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    fn explain_pattern(pattern: &str) -> String {
+        explain(&parse_expr(pattern.chars()).expect("parse"))
+    }
+
+    #[test]
+    fn test_phone_number() {
+        assert_eq!(
+            explain_pattern(r"^(\+\d{1,2}\s)?\(?\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}$"),
+            [
+                "start of line",
+                "then optionally:",
+                "  group 1:",
+                "    \"+\"",
+                "    then 1 or 2 digits, as many as possible",
+                "    then a whitespace character",
+                "then optionally \"(\"",
+                "then exactly 3 digits",
+                "then optionally \")\"",
+                "then one of a whitespace character, \".\" or \"-\"",
+                "then exactly 3 digits",
+                "then one of a whitespace character, \".\" or \"-\"",
+                "then exactly 4 digits",
+                "then end of line",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(
+            explain_pattern(r"(?<year>\d{4})-(\d\d)"),
+            [
+                "exactly 4 digits (group 1 \"year\")",
+                "then \"-\"",
+                "then group 2:",
+                "  a digit",
+                "  then a digit",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            explain_pattern("(cat|dog)s?"),
+            "either \"cat\", or \"dog\" (group 1)\nthen optionally \"s\""
+        );
+    }
+
+    #[test]
+    fn test_lazy_and_greedy() {
+        assert_eq!(
+            explain_pattern(".*"),
+            "zero or more characters other than newlines, as many as possible"
+        );
+        assert_eq!(
+            explain_pattern(".*?"),
+            "zero or more characters other than newlines, as few as possible"
+        );
+        assert_eq!(
+            explain_pattern("a{0,1}?"),
+            "optionally \"a\", preferably not"
+        );
+        assert_eq!(
+            explain_pattern("(?:ab){2,}?"),
+            "\"ab\", 2 or more times, as few as possible"
+        );
+        assert_eq!(
+            explain_pattern("[a-z_]{3}"),
+            "exactly 3 characters among \"a\" to \"z\" or \"_\""
+        );
+    }
+
+    #[test]
+    fn test_alternatives() {
+        assert_eq!(
+            explain_pattern(r"\bx|(?:a\s)+|"),
+            [
+                "either:",
+                "  a word boundary",
+                "  then \"x\"",
+                "or:",
+                "  one or more times, as many as possible:",
+                "    \"a\"",
+                "    then a whitespace character",
+                "or:",
+                "  nothing, the empty string",
+            ]
+            .join("\n")
+        );
+        assert_eq!(explain_pattern("[]"), "nothing, this never matches");
+    }
+
+    #[test]
+    fn test_single_characters() {
+        assert_eq!(explain_pattern("[x]"), "\"x\"");
+        assert_eq!(explain_pattern("a[.]b"), "\"a\"\nthen \".\"\nthen \"b\"");
+        assert_eq!(explain_pattern(r"\d{1}"), "a digit");
+        assert_eq!(explain_pattern(r"\d{1,1}?"), "a digit");
+        assert_eq!(explain_pattern("[a]{2}"), "\"a\", exactly 2 times");
+    }
+}
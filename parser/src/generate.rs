@@ -0,0 +1,590 @@
+//! Generation of strings matched by an expression, and of near misses: strings
+//! it rejects that are a single change away from one it matches.
+//!
+//! Candidates are built by walking the [`Token`] tree, then checked against
+//! the compiled automaton so that assertions such as `^` in the middle of an
+//! expression never let a rejected string through.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::Token,
+    compiler::{self, ranges},
+};
+
+/// Generates example strings for an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generator {
+    /// The most repetitions used for quantifiers without an upper bound,
+    /// unless their lower bound is higher.
+    pub max_repeat: usize,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self { max_repeat: 3 }
+    }
+}
+
+/// A small splitmix64 generator, good enough to pick examples and stable
+/// across platforms for a given seed.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`.
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+const PRINTABLE: (char, char) = (' ', '~');
+
+/// Orders strings shortest first, then alphabetically with control
+/// characters last.
+fn shortest_first(mut strings: Vec<String>, limit: usize) -> Vec<String> {
+    strings.sort_by_cached_key(|s| {
+        let chars: Vec<(bool, char)> = s.chars().map(|ch| (ch.is_control(), ch)).collect();
+        (chars.len(), chars)
+    });
+    strings.dedup();
+    strings.truncate(limit);
+    strings
+}
+
+fn concat(prefixes: &[String], suffixes: &[String], limit: usize) -> Vec<String> {
+    let strings = prefixes
+        .iter()
+        .flat_map(|prefix| {
+            suffixes
+                .iter()
+                .map(move |suffix| format!("{prefix}{suffix}"))
+        })
+        .collect();
+
+    shortest_first(strings, limit)
+}
+
+/// The characters of `ranges`, printable ones first.
+fn class_chars(ranges: &[(char, char)], limit: usize) -> Vec<char> {
+    let chars = ranges.iter().flat_map(|(lo, hi)| *lo..=*hi);
+    let printable = chars.clone().filter(|ch| !ch.is_control()).take(limit);
+    let control = chars.filter(|ch| ch.is_control()).take(limit);
+
+    printable.chain(control).take(limit).collect()
+}
+
+impl Generator {
+    /// The `count` shortest strings matched by `tok`, in order.
+    pub fn shortest(&self, tok: &Token, count: usize) -> Vec<String> {
+        let dfa = compiler::compile(tok);
+        let mut limit = count.max(1);
+
+        loop {
+            let candidates = self.expand(tok, limit);
+            let exhausted = candidates.len() < limit;
+            let accepted: Vec<String> = candidates
+                .into_iter()
+                .filter(|candidate| dfa.accepts(candidate))
+                .collect();
+
+            if accepted.len() >= count || exhausted || limit >= count * 64 {
+                return accepted.into_iter().take(count).collect();
+            }
+
+            limit *= 4;
+        }
+    }
+
+    /// Up to `count` distinct strings matched by `tok`, picked at random. The
+    /// same seed always gives the same strings.
+    pub fn sample(&self, tok: &Token, count: usize, seed: u64) -> Vec<String> {
+        let dfa = compiler::compile(tok);
+        let mut rng = Rng::new(seed);
+        let mut samples = vec![];
+
+        for _ in 0..count * 64 {
+            if samples.len() == count {
+                break;
+            }
+
+            let mut sample = String::new();
+            self.pick(tok, &mut rng, &mut sample);
+
+            if dfa.accepts(&sample) && !samples.contains(&sample) {
+                samples.push(sample);
+            }
+        }
+
+        samples
+    }
+
+    /// The range of repetitions tried for a quantifier.
+    pub(crate) fn repetitions(&self, min: usize, max: Option<usize>) -> (usize, usize) {
+        (min, max.unwrap_or(min.max(self.max_repeat)))
+    }
+
+    /// Up to `limit` of the shortest strings matched by `tok`, ignoring
+    /// assertions.
+    fn expand(&self, tok: &Token, limit: usize) -> Vec<String> {
+        if let Some(ranges) = ranges::token_ranges(tok) {
+            return class_chars(&ranges, limit)
+                .into_iter()
+                .map(String::from)
+                .collect();
+        }
+
+        match tok {
+            Token::Capturing(tok, _) => self.expand(tok, limit),
+            Token::Conjunction(tokens) => tokens.iter().fold(vec![String::new()], |acc, tok| {
+                concat(&acc, &self.expand(tok, limit), limit)
+            }),
+            Token::Disjunction(tokens) => shortest_first(
+                tokens
+                    .iter()
+                    .flat_map(|tok| self.expand(tok, limit))
+                    .collect(),
+                limit,
+            ),
+            Token::GreedyQuantifier(tok, min, max) | Token::LazyQuantifier(tok, min, max) => {
+                let (min, max) = self.repetitions(*min, *max);
+                let once = self.expand(tok, limit);
+                let mut repeated = vec![String::new()];
+                let mut strings = vec![];
+
+                for count in 0..=max {
+                    if count >= min {
+                        strings.extend(repeated.iter().cloned());
+                    }
+                    if count < max {
+                        repeated = concat(&repeated, &once, limit);
+                    }
+                }
+
+                shortest_first(strings, limit)
+            }
+            _ => vec![String::new()],
+        }
+    }
+
+    /// Appends a random string matched by `tok` to `out`, ignoring assertions.
+    pub(crate) fn pick(&self, tok: &Token, rng: &mut Rng, out: &mut String) {
+        if let Some(ranges) = ranges::token_ranges(tok) {
+            out.extend(pick_char(&ranges, rng));
+            return;
+        }
+
+        match tok {
+            Token::Capturing(tok, _) => self.pick(tok, rng, out),
+            Token::Conjunction(tokens) => {
+                for tok in tokens {
+                    self.pick(tok, rng, out);
+                }
+            }
+            Token::Disjunction(tokens) if !tokens.is_empty() => {
+                self.pick(&tokens[rng.below(tokens.len())], rng, out)
+            }
+            Token::GreedyQuantifier(tok, min, max) | Token::LazyQuantifier(tok, min, max) => {
+                let (min, max) = self.repetitions(*min, *max);
+                // An empty range matches nothing, which the caller checks.
+                let Some(extra) = max.checked_sub(min) else {
+                    return;
+                };
+
+                for _ in 0..min + rng.below(extra + 1) {
+                    self.pick(tok, rng, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A random character of `ranges`, printable ASCII when possible.
+pub(crate) fn pick_char(ranges: &[(char, char)], rng: &mut Rng) -> Option<char> {
+    let printable: Vec<(char, char)> = ranges
+        .iter()
+        .filter(|(lo, hi)| *lo <= PRINTABLE.1 && *hi >= PRINTABLE.0)
+        .map(|(lo, hi)| (*lo.max(&PRINTABLE.0), *hi.min(&PRINTABLE.1)))
+        .collect();
+    let ranges = match printable.is_empty() {
+        true => ranges,
+        false => &printable,
+    };
+    let size = |(lo, hi): &(char, char)| {
+        let surrogates = match *lo <= '\u{D7FF}' && *hi >= '\u{E000}' {
+            true => 0x800,
+            false => 0,
+        };
+        (*hi as usize) - (*lo as usize) + 1 - surrogates
+    };
+    let total: usize = ranges.iter().map(size).sum();
+
+    if total == 0 {
+        return None;
+    }
+
+    let mut index = rng.below(total);
+
+    for range in ranges {
+        match index.checked_sub(size(range)) {
+            Some(rest) => index = rest,
+            None => return (range.0..=range.1).nth(index),
+        }
+    }
+
+    None
+}
+
+/// How a near miss differs from a matched string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// A character the token does not match.
+    Character(char),
+    /// A number of repetitions outside the bounds of the quantifier.
+    Repetitions(usize),
+    /// A character inserted right before the start anchor.
+    BeforeStart(char),
+    /// A character inserted right after the end anchor.
+    AfterEnd(char),
+}
+
+impl Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mutation::Character(ch) => write!(f, "{ch:?} is not matched"),
+            Mutation::Repetitions(1) => write!(f, "repeated once"),
+            Mutation::Repetitions(count) => write!(f, "repeated {count} times"),
+            Mutation::BeforeStart(ch) => write!(f, "{ch:?} before the start anchor"),
+            Mutation::AfterEnd(ch) => write!(f, "{ch:?} after the end anchor"),
+        }
+    }
+}
+
+/// A string rejected by an expression, close to one it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss {
+    pub input: String,
+    /// The matched string `input` was derived from.
+    pub matched: String,
+    /// The token rejecting `input`.
+    pub token: Token,
+    pub mutation: Mutation,
+}
+
+/// The number of tokens in the tree rooted at `tok`.
+pub(crate) fn size(tok: &Token) -> usize {
+    1 + tok.children().iter().map(size).sum::<usize>()
+}
+
+/// A character outside `ranges`, right next to one of them when possible.
+fn near_char(ranges: &[(char, char)]) -> Option<char> {
+    ranges
+        .iter()
+        .flat_map(|(lo, hi)| [ranges::next_char(*hi), ranges::prev_char(*lo)])
+        .flatten()
+        .find(|ch| !ch.is_control() && !ranges::contains(ranges, *ch))
+        .or_else(|| class_chars(&ranges::complement(ranges), 1).pop())
+}
+
+/// The mutations applicable to the tokens of the tree rooted at `tok`, along
+/// with the pre-order index of the token they apply to.
+fn mutations(tok: &Token, id: &mut usize, found: &mut Vec<(usize, Mutation)>) {
+    let current = *id;
+
+    *id += 1;
+
+    if let Some(ranges) = ranges::token_ranges(tok) {
+        found.extend(near_char(&ranges).map(|ch| (current, Mutation::Character(ch))));
+        *id = current + size(tok);
+        return;
+    }
+
+    match tok {
+        Token::Start => found.push((current, Mutation::BeforeStart('x'))),
+        Token::End => found.push((current, Mutation::AfterEnd('x'))),
+        Token::GreedyQuantifier(_, min, max) | Token::LazyQuantifier(_, min, max) => {
+            if let Some(max) = max {
+                found.push((current, Mutation::Repetitions(max + 1)));
+            }
+            if *min > 0 {
+                found.push((current, Mutation::Repetitions(min - 1)));
+            }
+        }
+        _ => {}
+    }
+
+    for child in tok.children() {
+        mutations(child, id, found);
+    }
+}
+
+impl Generator {
+    /// Up to `count` strings rejected by `tok`, each obtained by breaking one
+    /// token of a short matched string: a character changed, a quantifier
+    /// pushed past its bounds or a character added around an anchor.
+    pub fn near_misses(&self, tok: &Token, count: usize) -> Vec<NearMiss> {
+        let dfa = compiler::compile(tok);
+        let mut found = vec![];
+        let mut misses: Vec<NearMiss> = vec![];
+
+        mutations(tok, &mut 0, &mut found);
+
+        for (target, mutation) in found {
+            if misses.len() == count {
+                break;
+            }
+
+            let mut matched = String::new();
+            let mut input = String::new();
+
+            self.write(tok, &mut 0, target, &mut None, &mut matched);
+            self.write(tok, &mut 0, target, &mut Some(mutation), &mut input);
+
+            if dfa.accepts(&matched)
+                && !dfa.accepts(&input)
+                && misses.iter().all(|miss| miss.input != input)
+            {
+                misses.push(NearMiss {
+                    input,
+                    matched,
+                    token: find(tok, target).clone(),
+                    mutation,
+                });
+            }
+        }
+
+        misses
+    }
+
+    /// Appends a short string matched by the tree rooted at `tok` to `out`,
+    /// making sure the token numbered `target` takes part in the match and
+    /// applying `mutation` to its first occurrence.
+    fn write(
+        &self,
+        tok: &Token,
+        id: &mut usize,
+        target: usize,
+        mutation: &mut Option<Mutation>,
+        out: &mut String,
+    ) {
+        let current = *id;
+        let end = current + size(tok);
+        let contains_target = (current..end).contains(&target);
+
+        *id = end;
+
+        if current == target {
+            match mutation.take() {
+                Some(Mutation::Character(ch)) => return out.push(ch),
+                Some(Mutation::BeforeStart(ch)) => out.push(ch),
+                Some(Mutation::Repetitions(count)) => {
+                    for _ in 0..count {
+                        self.write(
+                            &tok.children()[0],
+                            &mut (current + 1),
+                            target,
+                            &mut None,
+                            out,
+                        );
+                    }
+                    return;
+                }
+                other => *mutation = other,
+            }
+        }
+
+        if let Some(ranges) = ranges::token_ranges(tok) {
+            out.extend(class_chars(&ranges, 1));
+        } else {
+            match tok {
+                Token::Capturing(_, _) | Token::Conjunction(_) => {
+                    let mut child_id = current + 1;
+
+                    for child in tok.children() {
+                        self.write(child, &mut child_id, target, mutation, out);
+                    }
+                }
+                Token::Disjunction(tokens) => {
+                    let mut child_id = current + 1;
+                    let mut chosen = None;
+
+                    for child in tokens {
+                        if chosen.is_none() || (child_id..child_id + size(child)).contains(&target)
+                        {
+                            chosen = Some((child, child_id));
+                        }
+                        child_id += size(child);
+                    }
+
+                    if let Some((child, child_id)) = chosen {
+                        self.write(child, &mut { child_id }, target, mutation, out);
+                    }
+                }
+                Token::GreedyQuantifier(child, min, _) | Token::LazyQuantifier(child, min, _) => {
+                    let count = match contains_target && current != target {
+                        true => (*min).max(1),
+                        false => *min,
+                    };
+
+                    for _ in 0..count {
+                        self.write(child, &mut (current + 1), target, mutation, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let (true, Some(Mutation::AfterEnd(ch))) = (current == target, *mutation) {
+            out.push(ch);
+            *mutation = None;
+        }
+    }
+}
+
+/// The token numbered `target` in pre-order.
+fn find(tok: &Token, target: usize) -> &Token {
+    let mut id = 0;
+    let mut current = tok;
+
+    while id != target {
+        id += 1;
+
+        for child in current.children() {
+            if target < id + size(child) {
+                current = child;
+                break;
+            }
+            id += size(child);
+        }
+    }
+
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    fn parse(pattern: &str) -> Token {
+        parse_expr(pattern.chars()).expect("parse")
+    }
+
+    #[test]
+    fn test_shortest() {
+        let generator = Generator::default();
+
+        assert_eq!(
+            generator.shortest(&parse("ab?c*"), 5),
+            vec!["a", "ab", "ac", "abc", "acc"]
+        );
+        assert_eq!(
+            generator.shortest(&parse(r"\d{2}|x"), 4),
+            vec!["x", "00", "01", "02"]
+        );
+        assert_eq!(
+            generator.shortest(&parse("(?:a|b)+"), 3),
+            vec!["a", "b", "aa"]
+        );
+    }
+
+    #[test]
+    fn test_shortest_respects_assertions() {
+        let generator = Generator::default();
+
+        assert_eq!(generator.shortest(&parse(r"a\b[ b]"), 5), vec!["a "]);
+        assert_eq!(generator.shortest(&parse("a^b"), 5), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_max_repeat() {
+        let generator = Generator { max_repeat: 2 };
+
+        assert_eq!(generator.shortest(&parse("a*"), 10), vec!["", "a", "aa"]);
+        assert_eq!(generator.shortest(&parse("a{4,}"), 10), vec!["aaaa"]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let generator = Generator::default();
+        let tok = parse(r"[a-f]{2}\d+");
+        let samples = generator.sample(&tok, 10, 42);
+        let dfa = compiler::compile(&tok);
+
+        assert_eq!(samples.len(), 10);
+        assert!(samples.iter().all(|sample| dfa.accepts(sample)));
+        assert_eq!(samples, generator.sample(&tok, 10, 42));
+        assert_ne!(samples, generator.sample(&tok, 10, 43));
+    }
+
+    #[test]
+    fn test_empty_repetition_range() {
+        let generator = Generator::default();
+        let tok = parse("xa{3,1}|y");
+
+        assert_eq!(generator.shortest(&tok, 5), vec!["y"]);
+        assert_eq!(generator.sample(&tok, 5, 42), vec!["y"]);
+    }
+
+    fn near_misses(pattern: &str) -> Vec<(String, String, String)> {
+        Generator::default()
+            .near_misses(&parse(pattern), 20)
+            .into_iter()
+            .map(|miss| {
+                (
+                    miss.input,
+                    miss.token.to_string(),
+                    miss.mutation.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_near_misses() {
+        let misses = near_misses(r"^\d{3}-[a-c]+$");
+        let expected = [
+            ("x000-a", "^", "'x' before the start anchor"),
+            ("0000-a", "\\d{3}", "repeated 4 times"),
+            ("00-a", "\\d{3}", "repeated 2 times"),
+            (":00-a", "\\d", "':' is not matched"),
+            ("000.a", "-", "'.' is not matched"),
+            ("000-", "[a-c]+", "repeated 0 times"),
+            ("000-d", "[a-c]", "'d' is not matched"),
+            ("000-ax", "$", "'x' after the end anchor"),
+        ];
+
+        assert_eq!(
+            misses,
+            expected
+                .iter()
+                .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_near_misses_follow_the_mutated_branch() {
+        let misses = Generator::default().near_misses(&parse("ab|(?:cd)?e"), 20);
+        let dfa = compiler::compile(&parse("ab|(?:cd)?e"));
+
+        assert!(misses.iter().any(|miss| miss.input == "dde"));
+        assert!(misses
+            .iter()
+            .all(|miss| dfa.accepts(&miss.matched) && !dfa.accepts(&miss.input)));
+    }
+}
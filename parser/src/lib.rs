@@ -3,18 +3,35 @@ use std::{
     str::FromStr,
 };
 
-use parser::Token;
-pub use rendering::style::{Color, Format};
+use ast::{Span, Token};
+pub use rendering::{
+    glyphs::Charset,
+    style::{Color, Format},
+    theme::{Theme, BUILTIN_THEMES},
+    Layout, Options as RenderOptions,
+};
 
-use crate::rendering::Styled;
+use crate::rendering::block::{Block, Cell};
 
-mod compiler;
+pub mod ast;
+pub mod audit;
+pub mod compiler;
 pub mod error;
+pub mod explain;
+pub mod generate;
+pub mod lint;
 mod parser;
 mod rendering;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod simplify;
+pub mod trace;
 
 pub struct Regex {
     tok: Token,
+    spans: Vec<Span>,
+    /// The pattern the expression was parsed from.
+    source: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
@@ -34,31 +51,30 @@ impl From<rendering::style::Style> for Style {
     }
 }
 
-struct StyledOutput<'a, F>
-    where
-        F: Fn(&Style, &Arguments<'_>) -> String + 'a,
+struct StyledOutput<F>
+where
+    F: Fn(&Style, &Arguments<'_>) -> String,
 {
-    tok: &'a Token,
+    block: Block,
     style_func: F,
 }
 
-impl<'a, F> Display for StyledOutput<'a, F>
-    where
-        F: Fn(&Style, &Arguments<'_>) -> String + 'a,
+impl<F> Display for StyledOutput<F>
+where
+    F: Fn(&Style, &Arguments<'_>) -> String,
 {
+    /// Writes every row as runs of cells with the same style.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let block = rendering::render_token(&self.tok);
-        let mut dummy = Color::background_iter();
-
-        for ln in block.as_str().lines() {
-            write!(f, "{}", (self.style_func)(
-                &Style {
-                    background: dummy.next_color(),
-                    foreground: dummy.next_color(),
-                    ..Default::default()
-                },
-                &format_args!("{ln}"),
-            ))?;
+        for row in self.block.rows() {
+            for run in row.chunk_by(|a, b| a.style == b.style) {
+                let text: String = run.iter().map(Cell::text).collect();
+
+                write!(
+                    f,
+                    "{}",
+                    (self.style_func)(&run[0].style.into(), &format_args!("{text}"))
+                )?;
+            }
             writeln!(f, "{}", (self.style_func)(&Style::default(), &format_args!("")))?;
         }
 
@@ -67,12 +83,144 @@ impl<'a, F> Display for StyledOutput<'a, F>
 }
 
 impl Regex {
+    /// The syntax tree of the expression.
+    pub fn ast(&self) -> &Token {
+        &self.tok
+    }
+
+    /// Takes the syntax tree out of the expression, to rewrite it.
+    pub fn into_ast(self) -> Token {
+        self.tok
+    }
+
+    /// The source span of every token, in the order [`Token::walk`] visits
+    /// them. Empty when the expression was not parsed from a pattern.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
     pub fn with_style<'a, F>(&'a self, style_func: F) -> Box<dyn Display + 'a>
         where
             F: Fn(&Style, &Arguments<'_>) -> String + 'a,
+    {
+        self.with_options(&RenderOptions::default(), style_func)
+    }
+
+    /// Draws the diagram laid out as set by `options`, such as wrapped to the
+    /// width of a terminal.
+    pub fn with_options<'a, F>(
+        &'a self,
+        options: &RenderOptions,
+        style_func: F,
+    ) -> Box<dyn Display + 'a>
+    where
+        F: Fn(&Style, &Arguments<'_>) -> String + 'a,
     {
         Box::new(StyledOutput {
-            tok: &self.tok,
+            block: rendering::render_token_with(&self.tok, options),
+            style_func,
+        })
+    }
+
+    /// Looks for constructs prone to catastrophic backtracking, see
+    /// [`audit::audit`].
+    pub fn audit(&self) -> Vec<audit::Finding> {
+        audit::audit(&self.tok, &self.spans)
+    }
+
+    /// Describes the expression in plain English, see [`explain::explain`].
+    pub fn explain(&self) -> String {
+        explain::explain(&self.tok)
+    }
+
+    /// Checks the expression for frequent mistakes, see [`lint::lint`].
+    pub fn lint(&self, options: &lint::Options) -> Vec<lint::Diagnostic> {
+        lint::lint(&self.tok, &self.spans, options)
+    }
+
+    /// Draws the diagram as a standalone SVG document.
+    pub fn to_svg(&self) -> String {
+        rendering::svg::render(&rendering::render_token(&self.tok))
+    }
+
+    /// A standalone HTML page with the diagram and the pattern, where
+    /// hovering a token in either highlights it in both.
+    ///
+    /// Expressions that were not parsed from a pattern show their printed
+    /// form, without the highlighting of the pattern.
+    pub fn to_html(&self) -> String {
+        let block = rendering::render_token(&self.tok);
+
+        match &self.source {
+            Some(source) => rendering::html::render(&block, source, &self.spans),
+            None => rendering::html::render(&block, &self.tok.to_string(), &[]),
+        }
+    }
+
+    /// The syntax tree as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        rendering::dot::token(&self.tok)
+    }
+
+    /// The diagram as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        rendering::mermaid::token(&self.tok)
+    }
+
+    /// The diagram as a PlantUML activity diagram.
+    pub fn to_plantuml(&self) -> String {
+        rendering::plantuml::token(&self.tok)
+    }
+
+    /// The syntax tree and spans in their serializable form, see
+    /// [`schema`].
+    #[cfg(feature = "serde")]
+    pub fn to_document(&self) -> schema::Document {
+        schema::Document::new(self.tok.clone(), self.spans.clone())
+    }
+
+    /// The leftmost match in `input` and the path it took, see
+    /// [`trace::trace`]. Draw it with [`RenderOptions::trace`].
+    pub fn trace(&self, input: &str) -> Result<Option<trace::Trace>, trace::StepLimit> {
+        trace::trace(&self.tok, input)
+    }
+
+    /// Compiles the expression to a minimal deterministic automaton.
+    pub fn compile(&self) -> compiler::Dfa {
+        compiler::compile(&self.tok)
+    }
+
+    /// Returns an equivalent expression without redundant structure, see
+    /// [`simplify::simplify`].
+    pub fn simplify(&self) -> Regex {
+        Regex::from(simplify::simplify(self.tok.clone()))
+    }
+
+    /// Draws this expression and `other` side by side.
+    pub fn compare_with_style<'a, F>(
+        &'a self,
+        other: &'a Regex,
+        style_func: F,
+    ) -> Box<dyn Display + 'a>
+    where
+        F: Fn(&Style, &Arguments<'_>) -> String + 'a,
+    {
+        self.compare_with_options(other, &RenderOptions::default(), style_func)
+    }
+
+    /// Draws this expression and `other` side by side, each drawn as set by
+    /// `options`.
+    pub fn compare_with_options<'a, F>(
+        &'a self,
+        other: &'a Regex,
+        options: &RenderOptions,
+        style_func: F,
+    ) -> Box<dyn Display + 'a>
+    where
+        F: Fn(&Style, &Arguments<'_>) -> String + 'a,
+    {
+        Box::new(StyledOutput {
+            block: rendering::render_side_by_side(&self.tok, &other.tok, options),
             style_func,
         })
     }
@@ -84,17 +232,43 @@ impl Display for Regex {
     }
 }
 
+impl From<Token> for Regex {
+    fn from(tok: Token) -> Self {
+        Self {
+            tok,
+            spans: vec![],
+            source: None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<schema::Document> for Regex {
+    fn from(document: schema::Document) -> Self {
+        Self {
+            tok: document.ast,
+            spans: document.spans,
+            source: None,
+        }
+    }
+}
+
 impl FromStr for Regex {
     type Err = error::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tok = parser::parse_expr(s.chars())?;
+        let (tok, spans) = parser::parse_expr_with_spans(s.chars())?;
 
-        Ok(Self { tok })
+        Ok(Self {
+            tok,
+            spans,
+            source: Some(s.to_owned()),
+        })
     }
 }
 
 #[cfg(test)]
+#[allow(unused_must_use)]
 mod tests {
     use super::*;
 
@@ -0,0 +1,535 @@
+//! Checks for frequent mistakes in expressions.
+//!
+//! Each [`Rule`] looks at the syntax tree and reports [`Diagnostic`]s. Some
+//! come with a [`Fix`], an edit of the source pattern that [`apply_fixes`]
+//! can carry out. Fixes are only offered when the intent is clear; a
+//! misplaced anchor could be meant as a literal or be a mistake of its own,
+//! so it is only reported.
+
+use std::fmt::Display;
+
+use crate::{
+    ast::{Span, Token},
+    compiler::ranges,
+    generate::size,
+};
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The expression most likely does not match what was intended.
+    Warning,
+    /// Part of the expression can never match.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A check run by [`lint`].
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+pub const UNESCAPED_DOT: Rule = Rule {
+    id: "unescaped-dot",
+    severity: Severity::Warning,
+    description: "`.` between the parts of what looks like a domain name",
+};
+
+pub const MIXED_RANGE: Rule = Rule {
+    id: "mixed-range",
+    severity: Severity::Warning,
+    description: "a class range such as `A-z` spanning punctuation between letters or digits",
+};
+
+pub const DUPLICATE_CLASS_ITEM: Rule = Rule {
+    id: "duplicate-class-item",
+    severity: Severity::Warning,
+    description: "a class item already matched by the items before it",
+};
+
+pub const EMPTY_ALTERNATIVE: Rule = Rule {
+    id: "empty-alternative",
+    severity: Severity::Warning,
+    description: "an alternative matching only the empty string, as in `a|`",
+};
+
+pub const MISPLACED_ANCHOR: Rule = Rule {
+    id: "misplaced-anchor",
+    severity: Severity::Error,
+    description: "`^` after characters or `$` before characters, which can never match",
+};
+
+pub const QUANTIFIED_ANCHOR: Rule = Rule {
+    id: "quantified-anchor",
+    severity: Severity::Warning,
+    description: "a quantifier on an anchor or a word boundary",
+};
+
+pub const UNUSED_CAPTURE: Rule = Rule {
+    id: "unused-capture",
+    severity: Severity::Warning,
+    description: "a capturing group outside the groups in use, only checked when they are given with `--uses`",
+};
+
+/// Every rule, in the order their diagnostics are reported for a token.
+pub const RULES: [&Rule; 7] = [
+    &UNESCAPED_DOT,
+    &MIXED_RANGE,
+    &DUPLICATE_CLASS_ITEM,
+    &EMPTY_ALTERNATIVE,
+    &MISPLACED_ANCHOR,
+    &QUANTIFIED_ANCHOR,
+    &UNUSED_CAPTURE,
+];
+
+/// Replaces the characters of `span` in the source with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A problem found by a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: &'static Rule,
+    pub message: String,
+    /// Where the problem is in the source, when spans are known.
+    pub span: Option<Span>,
+    /// Only available when spans are known.
+    pub fix: Option<Fix>,
+}
+
+/// Settings of the rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Numbers or names of the capturing groups the caller refers to. When
+    /// set, the other groups are reported by [`UNUSED_CAPTURE`].
+    pub used_captures: Option<Vec<String>>,
+}
+
+/// Runs every rule on `tok`. `spans` are the source spans of its tokens in
+/// pre-order, as returned by [`crate::Regex::spans`], and may be empty.
+pub fn lint(tok: &Token, spans: &[Span], options: &Options) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        spans,
+        options,
+        groups: 0,
+        diagnostics: vec![],
+    };
+
+    linter.visit(tok, 0, 0, 0);
+    linter.diagnostics
+}
+
+/// Applies the fixes of `diagnostics` to `source`. A fix overlapping one
+/// applied before it is skipped.
+pub fn apply_fixes(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut fixes: Vec<&Fix> = diagnostics.iter().filter_map(|d| d.fix.as_ref()).collect();
+    let mut chars: Vec<char> = source.chars().collect();
+    let mut applied: Vec<Span> = vec![];
+
+    fixes.sort_by_key(|fix| std::cmp::Reverse((fix.span.start, fix.span.end)));
+
+    for fix in fixes {
+        let overlaps = applied
+            .iter()
+            .any(|span| fix.span.start < span.end && span.start < fix.span.end);
+
+        if !overlaps && fix.span.end <= chars.len() {
+            chars.splice(fix.span.start..fix.span.end, fix.replacement.chars());
+            applied.push(fix.span);
+        }
+    }
+
+    chars.into_iter().collect()
+}
+
+struct Linter<'a> {
+    spans: &'a [Span],
+    options: &'a Options,
+    /// Capturing groups numbered so far.
+    groups: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    /// Lints the tree rooted at `tok`, numbered `id` in pre-order. `before`
+    /// and `after` are the fewest characters the rest of the expression
+    /// consumes before and after it.
+    fn visit(&mut self, tok: &Token, id: usize, before: usize, after: usize) {
+        match tok {
+            Token::Conjunction(tokens) => self.sequence(tokens, id),
+            Token::Disjunction(tokens) if tok.is_class() => self.class(tokens, id),
+            Token::Disjunction(tokens) => self.alternatives(tokens, id),
+            Token::AsciiRange(lo, hi) => self.range(*lo, *hi, id),
+            Token::Start if before > 0 => self.report(
+                &MISPLACED_ANCHOR,
+                "`^` comes after characters, so it can never match; `\\^` matches a caret"
+                    .to_owned(),
+                id,
+                None,
+            ),
+            Token::End if after > 0 => self.report(
+                &MISPLACED_ANCHOR,
+                "`$` comes before characters, so it can never match; `\\$` matches a dollar sign"
+                    .to_owned(),
+                id,
+                None,
+            ),
+            Token::GreedyQuantifier(child, min, _) | Token::LazyQuantifier(child, min, _) => {
+                self.quantifier(child, *min, id)
+            }
+            Token::Capturing(_, name) => self.capture(name.as_deref(), id),
+            _ => {}
+        }
+
+        let mut child_id = id + 1;
+
+        match tok {
+            Token::Conjunction(tokens) => {
+                let lengths: Vec<usize> = tokens.iter().map(min_len).collect();
+
+                for (i, child) in tokens.iter().enumerate() {
+                    let before = before + lengths[..i].iter().sum::<usize>();
+                    let after = after + lengths[i + 1..].iter().sum::<usize>();
+
+                    self.visit(child, child_id, before, after);
+                    child_id += size(child);
+                }
+            }
+            _ => {
+                for child in tok.children() {
+                    self.visit(child, child_id, before, after);
+                    child_id += size(child);
+                }
+            }
+        }
+    }
+
+    fn span(&self, id: usize) -> Option<Span> {
+        self.spans.get(id).copied()
+    }
+
+    fn report(
+        &mut self,
+        rule: &'static Rule,
+        message: String,
+        id: usize,
+        replacement: Option<String>,
+    ) {
+        let span = self.span(id);
+
+        self.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            span,
+            fix: span
+                .zip(replacement)
+                .map(|(span, replacement)| Fix { span, replacement }),
+        });
+    }
+
+    /// Looks for dots in domain names such as `example.com`.
+    fn sequence(&mut self, tokens: &[Token], parent: usize) {
+        let mut id = parent + 1;
+
+        for (i, tok) in tokens.iter().enumerate() {
+            let domain_before = matches!(
+                tokens[..i].last(),
+                Some(Token::Literal(ch)) if ch.is_ascii_alphanumeric() || *ch == '-'
+            );
+            let domain_after = tokens[i + 1..]
+                .iter()
+                .take(2)
+                .filter(|tok| matches!(tok, Token::Literal(ch) if ch.is_ascii_lowercase()))
+                .count()
+                == 2;
+
+            if *tok == Token::Any && domain_before && domain_after {
+                self.report(
+                    &UNESCAPED_DOT,
+                    "`.` matches any character, not only a dot".to_owned(),
+                    id,
+                    Some("\\.".to_owned()),
+                );
+            }
+
+            id += size(tok);
+        }
+    }
+
+    fn class(&mut self, tokens: &[Token], parent: usize) {
+        let mut id = parent + 1;
+        let mut seen: ranges::Ranges = vec![];
+
+        for tok in tokens {
+            let item = ranges::token_ranges(tok).unwrap_or_default();
+            let covered = !item.is_empty()
+                && item.iter().all(|(lo, hi)| {
+                    seen.iter()
+                        .any(|(seen_lo, seen_hi)| seen_lo <= lo && hi <= seen_hi)
+                });
+
+            if covered {
+                self.report(
+                    &DUPLICATE_CLASS_ITEM,
+                    format!("`{tok}` is already matched by the class"),
+                    id,
+                    Some(String::new()),
+                );
+            }
+
+            seen = ranges::normalize([seen, item].concat());
+            id += size(tok);
+        }
+    }
+
+    fn alternatives(&mut self, tokens: &[Token], parent: usize) {
+        let mut id = parent + 1;
+
+        for tok in tokens {
+            if *tok == Token::Conjunction(vec![]) {
+                self.report(
+                    &EMPTY_ALTERNATIVE,
+                    "empty alternative, the alternation also matches the empty string".to_owned(),
+                    id,
+                    None,
+                );
+            }
+
+            id += size(tok);
+        }
+    }
+
+    fn range(&mut self, lo: char, hi: char, id: usize) {
+        let kinds = [('0', '9'), ('A', 'Z'), ('a', 'z')];
+        let kind = |ch: char| kinds.iter().position(|(lo, hi)| *lo <= ch && ch <= *hi);
+
+        let (Some(first), Some(last)) = (kind(lo), kind(hi)) else {
+            return;
+        };
+
+        if first >= last {
+            return;
+        }
+
+        let extra: String = (lo..=hi).filter(|ch| !ch.is_ascii_alphanumeric()).collect();
+        let replacement = kinds[first..=last]
+            .iter()
+            .map(|(kind_lo, kind_hi)| format!("{}-{}", lo.max(*kind_lo), hi.min(*kind_hi)))
+            .collect();
+
+        self.report(
+            &MIXED_RANGE,
+            format!("`{lo}-{hi}` also matches `{extra}`"),
+            id,
+            Some(replacement),
+        );
+    }
+
+    fn quantifier(&mut self, child: &Token, min: usize, id: usize) {
+        let anchor = match child {
+            Token::Conjunction(tokens) if tokens.len() == 1 => &tokens[0],
+            _ => child,
+        };
+
+        if !matches!(anchor, Token::Start | Token::End | Token::WordBoundary) {
+            return;
+        }
+
+        let (message, replacement) = match min {
+            0 => (
+                format!("`{anchor}` is optional, so it never applies"),
+                String::new(),
+            ),
+            _ => (
+                format!("repeating `{anchor}` has no effect"),
+                anchor.to_string(),
+            ),
+        };
+
+        self.report(&QUANTIFIED_ANCHOR, message, id, Some(replacement));
+    }
+
+    fn capture(&mut self, name: Option<&str>, id: usize) {
+        self.groups += 1;
+
+        let Some(used) = &self.options.used_captures else {
+            return;
+        };
+
+        let number = self.groups.to_string();
+
+        if used
+            .iter()
+            .any(|group| *group == number || Some(group.as_str()) == name)
+        {
+            return;
+        }
+
+        let group = match name {
+            Some(name) => format!("#{number} `{name}`"),
+            None => format!("#{number}"),
+        };
+        let span = self.span(id);
+        let content = self.span(id + 1);
+        // Not capturing would renumber the groups after this one.
+        let renumbers = used
+            .iter()
+            .filter_map(|group| group.parse::<usize>().ok())
+            .any(|used| used > self.groups);
+
+        self.diagnostics.push(Diagnostic {
+            rule: &UNUSED_CAPTURE,
+            message: format!("group {group} is never used, `(?:...)` does not capture"),
+            span,
+            fix: span
+                .zip(content)
+                .filter(|_| !renumbers)
+                .map(|(span, content)| Fix {
+                    span: Span::new(span.start, content.start),
+                    replacement: "(?:".to_owned(),
+                }),
+        });
+    }
+}
+
+/// The fewest characters `tok` consumes.
+fn min_len(tok: &Token) -> usize {
+    match tok {
+        Token::Conjunction(tokens) => tokens.iter().map(min_len).sum(),
+        Token::Disjunction(_) if tok.is_class() => 1,
+        Token::Disjunction(tokens) => tokens.iter().map(min_len).min().unwrap_or(0),
+        Token::Capturing(child, _) => min_len(child),
+        Token::GreedyQuantifier(child, min, _) | Token::LazyQuantifier(child, min, _) => {
+            min * min_len(child)
+        }
+        Token::Start | Token::End | Token::WordBoundary => 0,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr_with_spans;
+
+    fn lint_pattern(pattern: &str, options: &Options) -> Vec<Diagnostic> {
+        let (tok, spans) = parse_expr_with_spans(pattern.chars()).expect("parse");
+
+        lint(&tok, &spans, options)
+    }
+
+    fn ids(pattern: &str) -> Vec<&'static str> {
+        lint_pattern(pattern, &Options::default())
+            .iter()
+            .map(|diagnostic| diagnostic.rule.id)
+            .collect()
+    }
+
+    fn fixed(pattern: &str, options: &Options) -> String {
+        apply_fixes(pattern, &lint_pattern(pattern, options))
+    }
+
+    #[test]
+    fn test_unescaped_dot() {
+        assert_eq!(ids(r"https://example.com/"), vec!["unescaped-dot"]);
+        assert_eq!(
+            fixed(r"www.example.org", &Options::default()),
+            r"www\.example\.org"
+        );
+        assert_eq!(ids(r"a.b"), Vec::<&str>::new());
+        assert_eq!(ids(r"example\.com"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_mixed_range() {
+        let diagnostics = lint_pattern("[A-z0]", &Options::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "`A-z` also matches `[\\]^_``");
+        assert_eq!(diagnostics[0].span, Some(Span::new(1, 4)));
+        assert_eq!(apply_fixes("[A-z0]", &diagnostics), "[A-Za-z0]");
+        assert_eq!(fixed("[0-Z]", &Options::default()), "[0-9A-Z]");
+        assert_eq!(ids("[a-z0-9]"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_duplicate_class_item() {
+        assert_eq!(ids(r"[a-zqq\d]"), vec!["duplicate-class-item"; 2]);
+        assert_eq!(fixed(r"[\wa_x-]", &Options::default()), r"[\w-]");
+        assert_eq!(ids(r"[ab]"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_empty_alternative() {
+        let diagnostics = lint_pattern("(a||b)", &Options::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, &EMPTY_ALTERNATIVE);
+        assert_eq!(diagnostics[0].span, Some(Span::new(3, 3)));
+        assert_eq!(diagnostics[0].fix, None);
+    }
+
+    #[test]
+    fn test_misplaced_anchor() {
+        assert_eq!(ids("a^b"), vec!["misplaced-anchor"]);
+        assert_eq!(ids("a$b"), vec!["misplaced-anchor"]);
+        assert_eq!(fixed("1^2$3", &Options::default()), "1^2$3");
+        assert_eq!(ids(r"\b?^a|^b$"), vec!["quantified-anchor"]);
+        assert_eq!(ids("(?:^a)+"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_quantified_anchor() {
+        assert_eq!(fixed(r"^*a\b+", &Options::default()), r"a\b");
+        assert_eq!(ids("(?:$)?"), vec!["quantified-anchor"]);
+    }
+
+    #[test]
+    fn test_unused_capture() {
+        let pattern = r"(\d{4})-(?<month>\d\d)-(\d\d)";
+
+        assert_eq!(ids(pattern), Vec::<&str>::new());
+
+        let options = Options {
+            used_captures: Some(vec!["month".to_owned(), "3".to_owned()]),
+        };
+        let diagnostics = lint_pattern(pattern, &options);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "group #1 is never used, `(?:...)` does not capture"
+        );
+        assert_eq!(diagnostics[0].fix, None);
+        assert_eq!(
+            fixed(
+                pattern,
+                &Options {
+                    used_captures: Some(vec![])
+                }
+            ),
+            r"(?:\d{4})-(?:\d\d)-(?:\d\d)"
+        );
+
+        let uses = |group: &str| Options {
+            used_captures: Some(vec![group.to_owned()]),
+        };
+
+        assert_eq!(fixed("(a)(b)(c)", &uses("3")), "(a)(b)(c)");
+        assert_eq!(fixed("(a)(b)(c)", &uses("1")), "(a)(?:b)(?:c)");
+    }
+}
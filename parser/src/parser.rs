@@ -1,45 +1,119 @@
-use std::{collections::HashSet, iter::Peekable};
+use std::iter::Peekable;
+
+use crate::{
+    ast::{Span, Token},
+    error::{Error, Result},
+};
+
+/// The characters of an expression being parsed, along with the spans of
+/// the tokens built so far, in the order they were built.
+struct Input<I: Iterator<Item = (usize, char)>> {
+    chars: Peekable<I>,
+    /// Offset just past the last consumed character.
+    offset: usize,
+    spans: Vec<Span>,
+}
 
-use crate::error::{Error, Result};
+impl<I: Iterator<Item = (usize, char)>> Input<I> {
+    fn next_if(&mut self, func: impl FnOnce(&(usize, char)) -> bool) -> Option<(usize, char)> {
+        let item = self.chars.next_if(func);
+        self.consume(item)
+    }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-pub enum Token {
-    Capturing(Box<Token>, Option<String>),
-    Conjunction(Vec<Token>),
-    Disjunction(Vec<Token>),
-    Literal(char),
-    Start,
-    End,
-    Any,
-    GreedyQuantifier(Box<Token>, usize, Option<usize>),
-    LazyQuantifier(Box<Token>, usize, Option<usize>),
-    WordBoundary,
-    Alphanumeric,
-    Digit,
-    Whitespace,
-    NotAlphanumeric,
-    NotDigit,
-    NotWhitespace,
-    AsciiRange(char, char),
+    fn consume(&mut self, item: Option<(usize, char)>) -> Option<(usize, char)> {
+        if let Some((i, _)) = item {
+            self.offset = i + 1;
+        }
+        item
+    }
+
+    /// Records the span of a token from `start` to the current offset.
+    fn record(&mut self, start: usize) {
+        self.spans.push(Span::new(start, self.offset));
+    }
+}
+
+impl<I: Iterator<Item = (usize, char)>> Iterator for Input<I> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.chars.next();
+        self.consume(item)
+    }
 }
 
+#[cfg(test)]
 pub fn parse_expr(expr: impl IntoIterator<Item = char>) -> Result<Token> {
-    let mut chars = expr.into_iter().enumerate().peekable();
+    parse_expr_with_spans(expr).map(|(tok, _)| tok)
+}
+
+/// Parses `expr` along with the span of every token, in pre-order.
+pub fn parse_expr_with_spans(expr: impl IntoIterator<Item = char>) -> Result<(Token, Vec<Span>)> {
+    let mut chars = Input {
+        chars: expr.into_iter().enumerate().peekable(),
+        offset: 0,
+        spans: vec![],
+    };
+    let tok = parse_alternatives(&mut chars, false)?;
+    let spans = pre_order(&tok, &chars.spans);
+
+    Ok((tok, spans))
+}
+
+/// Reorders spans recorded as tokens were built, children first, to match a
+/// pre-order walk of `tok`.
+fn pre_order(tok: &Token, recorded: &[Span]) -> Vec<Span> {
+    fn visit(tok: &Token, recorded: &[Span], next: &mut usize, spans: &mut Vec<Span>) {
+        let slot = spans.len();
+
+        spans.push(Span::default());
+
+        for child in tok.children() {
+            visit(child, recorded, next, spans);
+        }
+
+        spans[slot] = recorded[*next];
+        *next += 1;
+    }
+
+    let mut spans = vec![];
+
+    visit(tok, recorded, &mut 0, &mut spans);
+    spans
+}
+
+/// Parses alternatives up to the end of input or, inside a group, up to the
+/// closing parenthesis.
+fn parse_alternatives(
+    chars: &mut Input<impl Iterator<Item = (usize, char)>>,
+    in_group: bool,
+) -> Result<Token> {
+    let start = chars.offset;
+    let mut alternative = start;
     let mut tokens = vec![];
     let mut disjunction = vec![];
 
-    loop {
+    let end = loop {
         match chars.next() {
-            Some((_, '|')) => disjunction.push(Token::Conjunction(tokens.drain(..).collect())),
-            Some((i, ch)) => tokens.push(parse_next(ch, i, &mut chars)?),
-            None => break,
+            None if in_group => return Err(Error::UnexpectedEndOfInput),
+            None => break chars.offset,
+            Some((i, ')')) if in_group => break i,
+            Some((i, '|')) => {
+                disjunction.push(Token::Conjunction(std::mem::take(&mut tokens)));
+                chars.spans.push(Span::new(alternative, i));
+                alternative = i + 1;
+            }
+            Some((i, ch)) => tokens.push(parse_next(ch, i, chars)?),
         };
-    }
+    };
+
+    chars.spans.push(Span::new(alternative, end));
 
     if disjunction.is_empty() {
         Ok(Token::Conjunction(tokens))
     } else {
-        disjunction.push(Token::Conjunction(tokens.drain(..).collect()));
+        disjunction.push(Token::Conjunction(tokens));
+        chars.spans.push(Span::new(start, end));
         Ok(Token::Disjunction(disjunction))
     }
 }
@@ -47,7 +121,7 @@ pub fn parse_expr(expr: impl IntoIterator<Item = char>) -> Result<Token> {
 fn parse_next(
     ch: char,
     position: usize,
-    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    chars: &mut Input<impl Iterator<Item = (usize, char)>>,
 ) -> Result<Token> {
     let tok = match ch {
         '?' | '*' | '+' | '{' => return Err(Error::UnexpectedChar(ch, position)),
@@ -60,45 +134,32 @@ fn parse_next(
         _ => Token::Literal(ch),
     };
 
-    parse_modifier(chars, tok)
+    chars.record(position);
+    parse_modifier(chars, tok, position)
 }
 
-fn parse_group(chars: &mut Peekable<impl Iterator<Item = (usize, char)>>) -> Result<Token> {
+fn parse_group(chars: &mut Input<impl Iterator<Item = (usize, char)>>) -> Result<Token> {
     let mut capturing = true;
     let mut name = None;
 
-    if let Some(_) = chars.next_if(|(_, ch)| *ch == '?') {
-        if let Some(_) = chars.next_if(|(_, ch)| *ch == '<') {
+    if chars.next_if(|(_, ch)| *ch == '?').is_some() {
+        if chars.next_if(|(_, ch)| *ch == '<').is_some() {
             name = Some(parse_group_name(chars)?);
-        } else if let Some(_) = chars.next_if(|(_, ch)| *ch == ':') {
+        } else if chars.next_if(|(_, ch)| *ch == ':').is_some() {
             capturing = false;
         }
     }
 
-    let mut tokens = vec![];
-    let mut disjunction = vec![];
-
-    loop {
-        match chars.next() {
-            None => return Err(Error::UnexpectedEndOfInput),
-            Some((_, ')')) => break,
-            Some((_, '|')) => disjunction.push(Token::Conjunction(tokens.drain(..).collect())),
-            Some((i, ch)) => tokens.push(parse_next(ch, i, chars)?),
-        };
-    }
-
-    let mut tok = if disjunction.is_empty() {
-        Token::Conjunction(tokens)
-    } else {
-        disjunction.push(Token::Conjunction(tokens.drain(..).collect()));
-        Token::Disjunction(disjunction)
-    };
+    let tok = parse_alternatives(chars, true)?;
 
     if capturing {
-        tok = Token::Capturing(Box::new(tok), name);
+        Ok(Token::Capturing(Box::new(tok), name))
+    } else {
+        // The group stands for its content, which takes the span of the
+        // whole group instead.
+        chars.spans.pop();
+        Ok(tok)
     }
-
-    Ok(tok)
 }
 
 fn parse_group_name(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<String> {
@@ -115,8 +176,9 @@ fn parse_group_name(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<S
 }
 
 fn parse_modifier(
-    chars: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+    chars: &mut Input<impl Iterator<Item = (usize, char)>>,
     tok: Token,
+    start: usize,
 ) -> Result<Token> {
     if let Some((_, ch)) = chars.next_if(|(_, ch)| "?*+{".contains(*ch)) {
         let new_tok = match ch {
@@ -140,6 +202,7 @@ fn parse_modifier(
             _ => panic!("Impossible! validated in the outer next_if"),
         };
 
+        chars.record(start);
         Ok(new_tok)
     } else {
         Ok(tok)
@@ -153,39 +216,47 @@ enum ChoiceToken {
     RangeStart(char),
 }
 
-fn parse_choice(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<Token> {
-    let mut choices = HashSet::new();
+fn parse_choice(chars: &mut Input<impl Iterator<Item = (usize, char)>>) -> Result<Token> {
+    let mut choices = vec![];
     let mut last = None;
+    // Start of the last item pushed, where a range ending later begins.
+    let mut item_start = 0;
 
     loop {
-        let tok = match chars.next() {
-            Some((_, ']')) => {
+        let (start, tok) = match chars.next() {
+            Some((i, ']')) => {
                 if let Some(ChoiceToken::RangeStart(_)) = last {
-                    choices.insert(Token::Literal('-'));
+                    choices.push(Token::Literal('-'));
+                    chars.spans.push(Span::new(i - 1, i));
                 }
                 break;
             }
-            Some((_, '\\')) => ChoiceToken::Token(parse_special(chars)?),
-            Some((_, '-')) => match last {
-                Some(ChoiceToken::Literal(ch)) => ChoiceToken::RangeStart(ch),
-                Some(ChoiceToken::Token(Token::Literal(ch))) => ChoiceToken::RangeStart(ch),
-                _ => ChoiceToken::Literal('-'),
+            Some((i, '\\')) => (i, ChoiceToken::Token(parse_special(chars)?)),
+            Some((i, '-')) => match last {
+                Some(ChoiceToken::Literal(ch)) => (i, ChoiceToken::RangeStart(ch)),
+                Some(ChoiceToken::Token(Token::Literal(ch))) => (i, ChoiceToken::RangeStart(ch)),
+                _ => (i, ChoiceToken::Literal('-')),
             },
-            Some((_, ch)) => ChoiceToken::Literal(ch),
+            Some((i, ch)) => (i, ChoiceToken::Literal(ch)),
             None => return Err(Error::UnexpectedEndOfInput),
         };
 
         match tok.clone() {
             ChoiceToken::Literal(ch) | ChoiceToken::Token(Token::Literal(ch)) => {
-                if let Some(ChoiceToken::RangeStart(start)) = last {
-                    choices.remove(&Token::Literal(start));
-                    choices.insert(Token::AsciiRange(start, ch));
+                if let Some(ChoiceToken::RangeStart(from)) = last {
+                    choices.pop();
+                    chars.spans.pop();
+                    choices.push(Token::AsciiRange(from, ch));
                 } else {
-                    choices.insert(Token::Literal(ch));
+                    choices.push(Token::Literal(ch));
+                    item_start = start;
                 }
+                chars.record(item_start);
             }
             ChoiceToken::Token(tok) => {
-                choices.insert(tok);
+                choices.push(tok);
+                item_start = start;
+                chars.record(item_start);
             }
             _ => {}
         };
@@ -193,7 +264,7 @@ fn parse_choice(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<Token
         last = Some(tok);
     }
 
-    Ok(Token::Disjunction(choices.into_iter().collect()))
+    Ok(Token::Disjunction(choices))
 }
 
 fn parse_range_quantifier(
@@ -209,16 +280,10 @@ fn parse_range_quantifier(
 
                 return match quantities.len() {
                     1 => Ok((
-                        quantities
-                            .get(0)
-                            .unwrap()
-                            .ok_or(Error::UnexpectedChar('}', pos))?,
-                        *quantities.get(0).unwrap(),
-                    )),
-                    2 => Ok((
-                        quantities.get(0).unwrap().unwrap_or_default(),
-                        *quantities.get(1).unwrap(),
+                        quantities[0].ok_or(Error::UnexpectedChar('}', pos))?,
+                        quantities[0],
                     )),
+                    2 => Ok((quantities[0].unwrap_or_default(), quantities[1])),
                     _ => Err(Error::UnexpectedChar('}', pos)),
                 };
             }
@@ -259,13 +324,50 @@ fn parse_special(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<Toke
         Some((_, '|')) => Ok(Token::Literal('|')),
         Some((_, '{')) => Ok(Token::Literal('{')),
         Some((_, '}')) => Ok(Token::Literal('}')),
+        Some((_, '?')) => Ok(Token::Literal('?')),
+        Some((_, '^')) => Ok(Token::Literal('^')),
+        Some((_, '$')) => Ok(Token::Literal('$')),
+        Some((_, '-')) => Ok(Token::Literal('-')),
         Some((_, 'b')) => Ok(Token::WordBoundary),
+        Some((_, 'u')) => parse_code_point(chars).map(Token::Literal),
         Some((i, ch)) => Err(Error::UnexpectedChar(ch, i)),
     }
 }
 
+fn parse_code_point(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<char> {
+    let mut buf = String::new();
+
+    match chars.next() {
+        Some((_, '{')) => {}
+        Some((pos, ch)) => return Err(Error::UnexpectedChar(ch, pos)),
+        None => return Err(Error::UnexpectedEndOfInput),
+    }
+
+    loop {
+        match chars.next() {
+            Some((pos, '}')) => {
+                return u32::from_str_radix(&buf, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(Error::UnexpectedChar('}', pos));
+            }
+            Some((_, ch)) if ch.is_ascii_hexdigit() && buf.len() < 6 => buf.push(ch),
+            Some((pos, ch)) => return Err(Error::UnexpectedChar(ch, pos)),
+            None => return Err(Error::UnexpectedEndOfInput),
+        }
+    }
+}
+
 #[cfg(test)]
+#[allow(
+    clippy::get_first,
+    clippy::into_iter_on_ref,
+    clippy::ptr_arg,
+    clippy::useless_conversion
+)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
     #[test]
@@ -284,6 +386,49 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_code_point() {
+        let tok = parse_expr("\\u{1F600}[\\u{0}-\\u{1f}]".chars()).expect("parsing should work");
+
+        assert_eq!(
+            tok,
+            Token::Conjunction(vec![
+                Token::Literal('\u{1F600}'),
+                Token::Disjunction(vec![Token::AsciiRange('\0', '\u{1f}')]),
+            ])
+        );
+        assert!(parse_expr("\\u{D800}".chars()).is_err());
+    }
+
+    #[test]
+    fn test_spans() {
+        let (_, spans) = parse_expr_with_spans("a(?:b|cd)+[x-z\\d-](?<n>e)".chars())
+            .expect("parsing should work");
+        let spans: Vec<(usize, usize)> = spans.iter().map(|span| (span.start, span.end)).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (0, 25),
+                (0, 1),
+                (1, 10),
+                (1, 9),
+                (4, 5),
+                (4, 5),
+                (6, 8),
+                (6, 7),
+                (7, 8),
+                (10, 18),
+                (11, 14),
+                (14, 16),
+                (16, 17),
+                (18, 25),
+                (23, 24),
+                (23, 24),
+            ]
+        );
+    }
+
     #[test]
     fn test_special_digit() {
         let tok = parse_expr("\\d".chars()).expect("parsing should work");
@@ -371,6 +516,20 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_choice_keeps_source_order() {
+        let tok = parse_expr("[z\\da-c]".chars()).expect("parsing should work");
+
+        assert_eq!(
+            tok,
+            Token::Conjunction(vec![Token::Disjunction(vec![
+                Token::Literal('z'),
+                Token::Digit,
+                Token::AsciiRange('a', 'c'),
+            ])])
+        )
+    }
+
     #[test]
     fn test_choice_special() {
         let tok = parse_expr("[\\]\\\\]".chars()).expect("parsing should work");
@@ -1,13 +1,99 @@
 use std::fmt::Display;
 
-use super::{style::Styles, Styled};
+use unicode_width::UnicodeWidthChar;
 
+use super::{style::Style, Styled};
+
+/// What the cell on the right of a wide character holds.
+const CONTINUATION: char = '\0';
+
+/// The number of cells `ch` takes on a terminal: 2 for wide East Asian
+/// characters and emoji, 0 for combining marks and other characters drawn
+/// over the previous one.
+pub fn char_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+/// The number of cells `text` takes on a terminal, see [`char_width`].
+pub fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// What a region of a block stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionKind {
+    /// A capturing group, with its number and name.
+    Group(usize, Option<String>),
+    /// A character class.
+    Class,
+    /// The label of a quantifier loop.
+    Label,
+    /// The cells drawn for a token, by pre-order index from the token the
+    /// block was rendered for.
+    Token(usize),
+}
+
+/// A rectangle of cells of a block standing for part of the expression,
+/// used by backends that draw more than characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub row: usize,
+    pub col: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A character of a block and how it is drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    /// Combining marks drawn over `ch`.
+    pub marks: Vec<char>,
+    pub style: Style,
+}
+
+impl Cell {
+    /// Whether the cell is the right half of the wide character on its left.
+    pub fn is_continuation(&self) -> bool {
+        self.ch == CONTINUATION
+    }
+
+    /// The character with its marks, nothing for the right half of a wide
+    /// character.
+    pub fn text(&self) -> String {
+        match self.is_continuation() {
+            true => String::new(),
+            false => std::iter::once(self.ch)
+                .chain(self.marks.iter().copied())
+                .collect(),
+        }
+    }
+
+    fn write(&mut self, ch: char) {
+        self.ch = ch;
+        self.marks.clear();
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            marks: vec![],
+            style: Style::default(),
+        }
+    }
+}
+
+/// A grid of cells, row by row, that diagrams are drawn into.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     height: usize,
     width: usize,
-    s: String,
-    styles: Styles,
+    cells: Vec<Cell>,
+    /// Innermost regions first.
+    regions: Vec<Region>,
 }
 
 impl Block {
@@ -15,45 +101,94 @@ impl Block {
         Self {
             width,
             height,
-            s: (" ".repeat(width) + "\n").repeat(height),
-            styles: Styles::new(width, height),
+            cells: vec![Cell::default(); width * height],
+            regions: vec![],
         }
     }
 
-    pub fn with_styles<F>(&mut self, func: F)
-    where
-        F: FnOnce(&mut Styles),
-    {
-        (func)(&mut self.styles);
+    /// Marks a rectangle of the block as standing for `kind`.
+    pub fn add_region(
+        &mut self,
+        kind: RegionKind,
+        row: usize,
+        col: usize,
+        width: usize,
+        height: usize,
+    ) {
+        self.regions.push(Region {
+            kind,
+            row,
+            col,
+            width,
+            height,
+        });
     }
 
-    pub fn set<T: Styled + ?Sized>(&mut self, row: usize, col: usize, s: &T) {
-        let mut row = row;
+    /// Marks the whole block as standing for `kind`.
+    pub fn add_outer_region(&mut self, kind: RegionKind) {
+        self.add_region(kind, 0, 0, self.width, self.height);
+    }
 
-        if let Some(styles) = s.style() {
-            self.styles.set(row, col, styles);
+    /// Renumbers the token regions for the block to be drawn as the token
+    /// `by` places after its parent in pre-order.
+    pub fn shift_tokens(&mut self, by: usize) {
+        for region in &mut self.regions {
+            if let RegionKind::Token(id) = &mut region.kind {
+                *id += by;
+            }
         }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
 
-        for ln in s.as_str().lines() {
-            let offset = row * (self.width + 1) + col;
-            let len = ln.chars().count();
-
-            let start = self
-                .s
-                .char_indices()
-                .nth(offset)
-                .expect("char at offset start")
-                .0;
-
-            let end = self
-                .s
-                .char_indices()
-                .nth(offset + len)
-                .expect("char at offset end")
-                .0;
-
-            self.s.replace_range(start..end, ln);
-            row += 1
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        (0..self.height).map(|row| &self.cells[row * self.width..(row + 1) * self.width])
+    }
+
+    pub fn cell_mut(&mut self, row: usize, col: usize) -> &mut Cell {
+        &mut self.cells[row * self.width + col]
+    }
+
+    /// Draws every cell of the block with `style`.
+    pub fn set_style(&mut self, style: Style) {
+        for cell in &mut self.cells {
+            cell.style = style;
+        }
+    }
+
+    /// Draws the cells of `width` columns from `col` on `row` with `style`
+    /// over their own.
+    pub fn apply_style(&mut self, row: usize, col: usize, width: usize, style: Style) {
+        for c in col..col + width {
+            self.cell_mut(row, c).style.apply(&style);
+        }
+    }
+
+    pub fn set<T: Styled + ?Sized>(&mut self, row: usize, col: usize, s: &T) {
+        s.draw(self, row, col);
+    }
+
+    /// Draws `text` like [`Block::set`], with `style` over the style of the
+    /// cells of its characters other than spaces.
+    pub fn set_styled(&mut self, row: usize, col: usize, text: &str, style: Style) {
+        self.set(row, col, text);
+
+        for (r, ln) in text.lines().enumerate() {
+            let mut c = col;
+
+            for ch in ln.chars() {
+                let width = char_width(ch);
+
+                if ch != ' ' {
+                    for cell in c..c + width {
+                        self.cell_mut(row + r, cell).style.apply(&style);
+                    }
+                }
+
+                c += width;
+            }
         }
     }
 
@@ -67,12 +202,46 @@ impl Block {
 }
 
 fn resize((width, height): (usize, usize), ln: &str) -> (usize, usize) {
-    (width.max(ln.chars().count()), height + 1)
+    (width.max(text_width(ln)), height + 1)
 }
 
 impl Display for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.s)
+        for row in self.rows() {
+            let row: String = row.iter().map(Cell::text).collect();
+
+            writeln!(f, "{row}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the characters only, the cells keep their style. Wide characters
+/// take two cells and zero width ones are drawn over the character before
+/// them.
+impl Styled for str {
+    fn draw(&self, block: &mut Block, row: usize, col: usize) {
+        for (r, ln) in self.lines().enumerate() {
+            let mut c = col;
+            let mut last = None;
+
+            for ch in ln.chars() {
+                match char_width(ch) {
+                    0 => block.cell_mut(row + r, last.unwrap_or(c)).marks.push(ch),
+                    width => {
+                        block.cell_mut(row + r, c).write(ch);
+
+                        for continuation in c + 1..c + width {
+                            block.cell_mut(row + r, continuation).write(CONTINUATION);
+                        }
+
+                        last = Some(c);
+                        c += width;
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -86,18 +255,25 @@ impl From<&str> for Block {
     }
 }
 
-impl AsRef<str> for Block {
-    fn as_ref(&self) -> &str {
-        &self.s
-    }
-}
-
+/// Copies the cells, with their style, and the regions.
 impl Styled for Block {
-    fn as_str(&self) -> &str {
-        self.as_ref()
-    }
+    fn draw(&self, block: &mut Block, row: usize, col: usize) {
+        for (r, cells) in self.rows().enumerate() {
+            for (c, cell) in cells.iter().enumerate() {
+                let target = block.cell_mut(row + r, col + c);
+
+                target.ch = cell.ch;
+                target.marks.clone_from(&cell.marks);
+                target.style = cell.style;
+            }
+        }
 
-    fn style(&self) -> Option<&Styles> {
-        Some(&self.styles)
+        block
+            .regions
+            .extend(self.regions.iter().map(|region| Region {
+                row: region.row + row,
+                col: region.col + col,
+                ..region.clone()
+            }));
     }
 }
@@ -0,0 +1,170 @@
+//! Graphviz DOT output of syntax trees and automata.
+
+use std::fmt::Write;
+
+use crate::{
+    ast::Token,
+    compiler::{Dfa, Edge, Nfa},
+};
+
+fn quote(label: &str) -> String {
+    format!("\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn token_label(tok: &Token) -> String {
+    let quantifier = |kind: &str, min: &usize, max: &Option<usize>| match max {
+        Some(max) => format!("{kind} {{{min},{max}}}"),
+        None => format!("{kind} {{{min},}}"),
+    };
+
+    match tok {
+        Token::Capturing(_, Some(name)) => format!("Capturing {name:?}"),
+        Token::Capturing(_, None) => "Capturing".to_owned(),
+        Token::Conjunction(_) => "Conjunction".to_owned(),
+        Token::Disjunction(_) if tok.is_class() => "Disjunction (class)".to_owned(),
+        Token::Disjunction(_) => "Disjunction".to_owned(),
+        Token::Literal(ch) => format!("Literal {ch:?}"),
+        Token::GreedyQuantifier(_, min, max) => quantifier("GreedyQuantifier", min, max),
+        Token::LazyQuantifier(_, min, max) => quantifier("LazyQuantifier", min, max),
+        Token::AsciiRange(lo, hi) => format!("AsciiRange {lo:?}-{hi:?}"),
+        _ => format!("{tok:?}"),
+    }
+}
+
+/// The tree rooted at `tok`, nodes numbered in pre-order.
+pub fn token(tok: &Token) -> String {
+    fn visit(tok: &Token, id: &mut usize, out: &mut String) {
+        let current = *id;
+
+        *id += 1;
+
+        let _ = writeln!(out, "  n{current} [label={}];", quote(&token_label(tok)));
+
+        for child in tok.children() {
+            let _ = writeln!(out, "  n{current} -> n{id};");
+            visit(child, id, out);
+        }
+    }
+
+    let mut out = String::from("digraph ast {\n  node [shape=box, fontname=\"monospace\"];\n");
+
+    visit(tok, &mut 0, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+fn automaton_header(name: &str, start: usize) -> String {
+    format!(
+        "digraph {name} {{\n  rankdir=LR;\n  node [shape=circle, fontname=\"monospace\"];\n  \
+         edge [fontname=\"monospace\"];\n  start [shape=point];\n  start -> {start};\n"
+    )
+}
+
+/// Every state of `nfa`, with epsilon edges dashed.
+pub fn nfa(nfa: &Nfa) -> String {
+    let mut out = automaton_header("nfa", nfa.start());
+
+    let _ = writeln!(out, "  {} [shape=doublecircle];", nfa.accept());
+
+    for (from, state) in nfa.states().iter().enumerate() {
+        for (edge, to) in &state.transitions {
+            let style = match edge {
+                Edge::Ranges(_) => "",
+                _ => ", style=dashed",
+            };
+
+            let _ = writeln!(
+                out,
+                "  {from} -> {to} [label={}{style}];",
+                quote(&edge.label())
+            );
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// The live states of `dfa`, see [`Dfa::edges`].
+pub fn dfa(dfa: &Dfa) -> String {
+    let mut out = automaton_header("dfa", dfa.start());
+    let alive = dfa.live_states();
+
+    for state in (0..dfa.state_count()).filter(|state| alive[*state] && dfa.is_accepting(*state)) {
+        let _ = writeln!(out, "  {state} [shape=doublecircle];");
+    }
+
+    for (from, label, to) in dfa.edges() {
+        let _ = writeln!(
+            out,
+            "  {from} -> {to} [label={}];",
+            quote(&label.to_string())
+        );
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compiler, parser::parse_expr};
+
+    #[test]
+    fn test_token() {
+        let tok = parse_expr(r#"(?<q>"\d)+"#.chars()).expect("parse");
+
+        assert_eq!(
+            token(&tok),
+            [
+                "digraph ast {",
+                "  node [shape=box, fontname=\"monospace\"];",
+                "  n0 [label=\"Conjunction\"];",
+                "  n0 -> n1;",
+                "  n1 [label=\"GreedyQuantifier {1,}\"];",
+                "  n1 -> n2;",
+                "  n2 [label=\"Capturing \\\"q\\\"\"];",
+                "  n2 -> n3;",
+                "  n3 [label=\"Conjunction\"];",
+                "  n3 -> n4;",
+                "  n4 [label=\"Literal '\\\"'\"];",
+                "  n3 -> n5;",
+                "  n5 [label=\"Digit\"];",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_nfa() {
+        let dot = nfa(&Nfa::new(&parse_expr("^a*".chars()).expect("parse")));
+
+        assert!(dot.contains("start -> 0;"));
+        assert!(dot.contains("[label=\"^\", style=dashed];"));
+        assert!(dot.contains("[label=\"ε\", style=dashed];"));
+        assert!(dot.contains("[label=\"a\"];"));
+    }
+
+    #[test]
+    fn test_dfa() {
+        let dot = dfa(&compiler::compile(
+            &parse_expr(r"\d+|x".chars()).expect("parse"),
+        ));
+
+        assert!(dot.ends_with(
+            &[
+                "  2 [shape=doublecircle];",
+                "  3 [shape=doublecircle];",
+                "  0 -> 2 [label=\"\\\\d\"];",
+                "  0 -> 3 [label=\"x\"];",
+                "  2 -> 2 [label=\"\\\\d\"];",
+                "}",
+                "",
+            ]
+            .join("\n")
+        ));
+    }
+}
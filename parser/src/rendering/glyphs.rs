@@ -0,0 +1,104 @@
+/// The sets of characters diagrams can be drawn with. Every set draws the
+/// same layout, one character per cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    /// Box-drawing characters, with rounded corners on the tracks.
+    #[default]
+    Rounded,
+    /// Box-drawing characters, with square corners everywhere.
+    Square,
+    /// `+`, `-` and `|`, for terminals and logs that mangle the others.
+    Ascii,
+}
+
+impl Charset {
+    pub fn glyphs(self) -> &'static Glyphs {
+        match self {
+            Charset::Rounded => &ROUNDED,
+            Charset::Square => &SQUARE,
+            Charset::Ascii => &ASCII,
+        }
+    }
+}
+
+/// The characters tracks and group frames are drawn with. Corners and tees
+/// are named after the side of the cell they are on, like `top_left` for
+/// `╭` and `tee_down` for `┬`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyphs {
+    pub horizontal: char,
+    /// The horizontal track of lazy quantifiers.
+    pub dashed: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub tee_down: char,
+    pub tee_up: char,
+    pub tee_right: char,
+    pub tee_left: char,
+    pub cross: char,
+    pub frame_top_left: char,
+    pub frame_top_right: char,
+    pub frame_bottom_left: char,
+    pub frame_bottom_right: char,
+    /// The upper bound of quantifiers without one.
+    pub infinity: &'static str,
+    /// Marks the alternatives a match took.
+    pub check: char,
+    /// Comes before the number of times something happened in a match.
+    pub times: char,
+}
+
+pub const ROUNDED: Glyphs = Glyphs {
+    horizontal: '─',
+    dashed: '╌',
+    vertical: '│',
+    top_left: '╭',
+    top_right: '╮',
+    bottom_left: '╰',
+    bottom_right: '╯',
+    tee_down: '┬',
+    tee_up: '┴',
+    tee_right: '├',
+    tee_left: '┤',
+    cross: '┼',
+    frame_top_left: '┌',
+    frame_top_right: '┐',
+    frame_bottom_left: '└',
+    frame_bottom_right: '┘',
+    infinity: "∞",
+    check: '✓',
+    times: '×',
+};
+
+pub const SQUARE: Glyphs = Glyphs {
+    top_left: '┌',
+    top_right: '┐',
+    bottom_left: '└',
+    bottom_right: '┘',
+    ..ROUNDED
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    horizontal: '-',
+    dashed: '~',
+    vertical: '|',
+    top_left: '+',
+    top_right: '+',
+    bottom_left: '+',
+    bottom_right: '+',
+    tee_down: '+',
+    tee_up: '+',
+    tee_right: '+',
+    tee_left: '+',
+    cross: '+',
+    frame_top_left: '+',
+    frame_top_right: '+',
+    frame_bottom_left: '+',
+    frame_bottom_right: '+',
+    infinity: "*",
+    check: '*',
+    times: 'x',
+};
@@ -0,0 +1,358 @@
+//! HTML output of the diagrams, linked to the pattern they were drawn for.
+//!
+//! The diagram and the pattern are both written as runs of characters in
+//! `<span>`s. Every run has a `tN` class for each token whose cells or
+//! source span cover it, and the innermost token in `data-t`. Hovering a run
+//! highlights everything with the class of its innermost token, on both
+//! sides. The cell styles become classes too, such as `fg-blue bold`.
+
+use std::fmt::Write;
+
+use super::{
+    block::{Block, Cell, RegionKind},
+    style::{Color, Format, Style},
+};
+use crate::ast::Span;
+
+const STYLE: &str = "\
+body{font-family:sans-serif;margin:2em;color:#111;background:#fff}\
+pre{font-family:monospace;font-size:16px;line-height:1.25}\
+pre.source{display:inline-block;padding:.25em .5em;border:1px solid #ccc;border-radius:4px}\
+[data-t]{cursor:default}\
+pre .hl{background:#ffe58f;color:#111}";
+
+const SCRIPT: &str = "\
+for (const pre of document.querySelectorAll('pre')) {\
+ pre.addEventListener('mouseover', (event) => highlight(event.target.dataset.t));\
+ pre.addEventListener('mouseout', () => highlight());\
+}\
+function highlight(id) {\
+ for (const el of document.querySelectorAll('.hl')) el.classList.remove('hl');\
+ if (id !== undefined) for (const el of document.querySelectorAll('.t' + id)) el.classList.add('hl');\
+}";
+
+/// The colors of the 16-color palette, in the order of the 256-color one.
+const COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::LightBlack,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::LightWhite,
+];
+
+/// The class name and CSS value of a color, `None` for the default one.
+fn color(color: Color) -> Option<(String, String)> {
+    let (name, value) = match color {
+        Color::Reset => return None,
+        Color::Black => ("black", "#000"),
+        Color::Red => ("red", "#c62828"),
+        Color::Green => ("green", "#2e7d32"),
+        Color::Yellow => ("yellow", "#b58900"),
+        Color::Blue => ("blue", "#1e5bd6"),
+        Color::Magenta => ("magenta", "#a626a4"),
+        Color::Cyan => ("cyan", "#0e8a9c"),
+        Color::White => ("white", "#ddd"),
+        Color::LightBlack => ("light-black", "#777"),
+        Color::LightRed => ("light-red", "#ef5350"),
+        Color::LightGreen => ("light-green", "#66bb6a"),
+        Color::LightYellow => ("light-yellow", "#fdd835"),
+        Color::LightBlue => ("light-blue", "#64b5f6"),
+        Color::LightMagenta => ("light-magenta", "#ce93d8"),
+        Color::LightCyan => ("light-cyan", "#4dd0e1"),
+        Color::LightWhite => ("light-white", "#fff"),
+        Color::Ansi256(value) => return Some((format!("256-{value}"), ansi256(value))),
+        Color::Rgb(r, g, b) => {
+            let hex = format!("{r:02x}{g:02x}{b:02x}");
+
+            return Some((format!("rgb-{hex}"), format!("#{hex}")));
+        }
+    };
+
+    Some((name.to_owned(), value.to_owned()))
+}
+
+/// The CSS value of a color of the 256-color palette: the 16 colors, then
+/// a 6×6×6 cube and a ramp of grays.
+fn ansi256(value: u8) -> String {
+    let value = value as usize;
+
+    match value {
+        0..=15 => color(COLORS[value])
+            .map(|(_, value)| value)
+            .unwrap_or_default(),
+        16..=231 => {
+            let level = |n: usize| if n == 0 { 0 } else { 55 + 40 * n };
+            let cube = value - 16;
+
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                level(cube / 36),
+                level(cube / 6 % 6),
+                level(cube % 6)
+            )
+        }
+        _ => {
+            let gray = 8 + 10 * (value - 232);
+
+            format!("#{gray:02x}{gray:02x}{gray:02x}")
+        }
+    }
+}
+
+fn format(format: Format) -> Option<(&'static str, &'static str)> {
+    let format = match format {
+        Format::Reset => return None,
+        Format::Bold => ("bold", "font-weight:bold"),
+        Format::Dim => ("dim", "opacity:.6"),
+        Format::Underline => ("underline", "text-decoration:underline"),
+        Format::Reverse => ("reverse", "filter:invert(1)"),
+        Format::Italic => ("italic", "font-style:italic"),
+    };
+
+    Some(format)
+}
+
+/// The rules of the classes of `block`: every color of the 16-color palette
+/// and the other colors its cells use.
+fn style_sheet(block: &Block) -> String {
+    let mut css = STYLE.to_owned();
+    let mut colors = COLORS.to_vec();
+
+    for cell in block.rows().flatten() {
+        for color in [cell.style.foreground, cell.style.background]
+            .into_iter()
+            .flatten()
+        {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+    }
+
+    for (name, value) in colors.into_iter().filter_map(color) {
+        let _ = write!(
+            css,
+            ".fg-{name}{{color:{value}}}.bg-{name}{{background:{value}}}"
+        );
+    }
+
+    for (name, value) in [
+        Format::Bold,
+        Format::Dim,
+        Format::Underline,
+        Format::Reverse,
+        Format::Italic,
+    ]
+    .into_iter()
+    .filter_map(format)
+    {
+        let _ = write!(css, ".{name}{{{value}}}");
+    }
+
+    css
+}
+
+fn style_classes(style: &Style) -> Vec<String> {
+    let foreground = style
+        .foreground
+        .and_then(color)
+        .map(|(name, _)| format!("fg-{name}"));
+    let background = style
+        .background
+        .and_then(color)
+        .map(|(name, _)| format!("bg-{name}"));
+    let format = style
+        .format
+        .and_then(format)
+        .map(|(name, _)| name.to_owned());
+
+    [foreground, background, format]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes `chars`, the text of each column, as runs of columns covered by the
+/// same tokens, innermost token first, and with the same extra classes.
+fn write_runs(
+    html: &mut String,
+    chars: &[String],
+    cover: impl Fn(usize) -> (Vec<usize>, Vec<String>),
+) {
+    let mut col = 0;
+
+    while col < chars.len() {
+        let (tokens, classes) = cover(col);
+        let start = col;
+
+        while col < chars.len() && cover(col) == (tokens.clone(), classes.clone()) {
+            col += 1;
+        }
+
+        let text = escape(&chars[start..col].concat());
+
+        match tokens.first() {
+            None if classes.is_empty() => html.push_str(&text),
+            None => {
+                let _ = write!(html, r#"<span class="{}">{text}</span>"#, classes.join(" "));
+            }
+            Some(innermost) => {
+                let classes: Vec<String> = tokens
+                    .iter()
+                    .map(|id| format!("t{id}"))
+                    .chain(classes)
+                    .collect();
+                let _ = write!(
+                    html,
+                    r#"<span class="{}" data-t="{innermost}">{text}</span>"#,
+                    classes.join(" ")
+                );
+            }
+        }
+    }
+}
+
+/// A standalone page with `block` and `source`, the pattern it was drawn
+/// for. `spans` are the source spans of the tokens of the block, in
+/// pre-order; the pattern is not linked to the diagram when empty.
+pub fn render(block: &Block, source: &str, spans: &[Span]) -> String {
+    let mut html = String::new();
+    let title = escape(source);
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>trex: {title}</title>\n\
+         <style>{}</style>\n</head>\n<body>\n",
+        style_sheet(block)
+    );
+
+    html.push_str("<pre class=\"source\">");
+    write_source(&mut html, source, spans);
+    html.push_str("</pre>\n<pre class=\"diagram\">");
+    write_diagram(&mut html, block);
+    let _ = write!(
+        html,
+        "</pre>\n<script>{SCRIPT}</script>\n</body>\n</html>\n"
+    );
+
+    html
+}
+
+fn write_source(html: &mut String, source: &str, spans: &[Span]) {
+    let chars: Vec<String> = source.chars().map(String::from).collect();
+
+    write_runs(html, &chars, |col| {
+        let mut tokens: Vec<usize> = (0..spans.len())
+            .filter(|id| (spans[*id].start..spans[*id].end).contains(&col))
+            .collect();
+
+        // Innermost first: the shortest span, and the deepest token among
+        // the ones with the same span.
+        tokens.sort_by_key(|id| (spans[*id].len(), usize::MAX - id));
+        (tokens, vec![])
+    });
+}
+
+fn write_diagram(html: &mut String, block: &Block) {
+    for (row, cells) in block.rows().enumerate() {
+        let chars: Vec<String> = cells.iter().map(Cell::text).collect();
+
+        write_runs(html, &chars, |col| {
+            let tokens = block
+                .regions()
+                .iter()
+                .filter(|region| {
+                    (region.row..region.row + region.height).contains(&row)
+                        && (region.col..region.col + region.width).contains(&col)
+                })
+                .filter_map(|region| match region.kind {
+                    RegionKind::Token(id) => Some(id),
+                    _ => None,
+                })
+                .collect();
+            let classes = style_classes(&cells[col].style);
+
+            (tokens, classes)
+        });
+        html.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Token, parser::parse_expr_with_spans, rendering::render_token};
+
+    fn html(pattern: &str) -> String {
+        let (tok, spans) = parse_expr_with_spans(pattern.chars()).expect("parse");
+
+        render(&render_token(&tok), pattern, &spans)
+    }
+
+    #[test]
+    fn test_document() {
+        let html = html("a<b");
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>trex: a&lt;b</title>"));
+        assert!(html.ends_with("</script>\n</body>\n</html>\n"));
+        assert!(html.contains(r#"<span class="t1 t0" data-t="1">a</span>"#));
+        assert!(html.contains(r#"<span class="t2 t0" data-t="2">&lt;</span>"#));
+    }
+
+    #[test]
+    fn test_linking() {
+        let html = html(r"(\d)+");
+        let (source, diagram) = html.split_once("<pre class=\"diagram\">").expect("diagram");
+
+        // The digit is token 4, under the conjunction, quantifier, group
+        // and the conjunction inside it.
+        assert!(source.contains(r#"<span class="t4 t3 t2 t1 t0" data-t="4">\d</span>"#));
+        assert!(
+            diagram.contains(r#"<span class="t4 t3 t2 t1 t0 fg-blue bold" data-t="4">\d</span>"#)
+        );
+        // The frame belongs to the group, the loop below it to the quantifier.
+        assert!(diagram.contains(r#"<span class="t2 t1 t0 fg-green" data-t="2">┌ #1 ┐</span>"#));
+        assert!(diagram.contains(r#"<span class="t1 t0 fg-yellow" data-t="1">╰────╯</span>"#));
+    }
+
+    #[test]
+    fn test_extended_colors() {
+        let mut block = Block::from("ab");
+
+        block.cell_mut(0, 0).style.foreground = Some(Color::Ansi256(208));
+        block.cell_mut(0, 1).style.background = Some(Color::Rgb(0x12, 0x34, 0x56));
+
+        let html = render(&block, "ab", &[]);
+
+        assert!(html.contains(".fg-256-208{color:#ff8700}"));
+        assert!(html.contains(".bg-rgb-123456{background:#123456}"));
+        assert!(html
+            .contains(r#"<span class="fg-256-208">a</span><span class="bg-rgb-123456">b</span>"#));
+    }
+
+    #[test]
+    fn test_unlinked() {
+        let html = render(&render_token(&Token::Any), ".", &[]);
+
+        assert!(html.contains("<pre class=\"source\">.</pre>"));
+    }
+}
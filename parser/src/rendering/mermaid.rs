@@ -0,0 +1,285 @@
+//! Mermaid output of diagrams and automata.
+//!
+//! Expressions become a left to right flowchart laid out like the text
+//! diagrams: alternatives branch out and join again, quantifiers loop back
+//! with their bounds as label and capturing groups are subgraphs titled with
+//! their number and name. Automata become state diagrams.
+
+use std::fmt::Write;
+
+use super::{parts, quantifier_label, Part, Transitions};
+use crate::{
+    ast::Token,
+    compiler::{Dfa, Nfa},
+};
+
+/// `text` as a quoted label, with the characters Mermaid would interpret
+/// written as entities.
+fn quote(text: &str) -> String {
+    let text = text
+        .replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;");
+
+    format!("\"{text}\"")
+}
+
+struct Flowchart {
+    out: String,
+    nodes: usize,
+    groups: usize,
+    /// Nesting of the subgraphs being written.
+    depth: usize,
+}
+
+impl Flowchart {
+    fn line(&mut self, line: &str) {
+        let _ = writeln!(self.out, "{}{line}", "    ".repeat(self.depth + 1));
+    }
+
+    /// Declares a node drawn between `open` and `close`, such as `[` and `]`.
+    fn node(&mut self, open: &str, label: &str, close: &str) -> String {
+        let id = format!("n{}", self.nodes);
+
+        self.nodes += 1;
+        self.line(&format!("{id}{open}{}{close}", quote(label)));
+        id
+    }
+
+    /// Declares a point where tracks split or join.
+    fn junction(&mut self) -> String {
+        let id = format!("j{}", self.nodes);
+
+        self.nodes += 1;
+        self.line(&format!("{id}(( ))"));
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        match label {
+            Some(label) => self.line(&format!("{from} -.->|{}| {to}", quote(label))),
+            None => self.line(&format!("{from} --> {to}")),
+        }
+    }
+
+    /// Draws `tok` after the node `from`, returning the node it ends on.
+    fn build(&mut self, tok: &Token, from: String) -> String {
+        match tok {
+            Token::Conjunction(tokens) => {
+                parts(tokens)
+                    .into_iter()
+                    .fold(from, |from, part| match part {
+                        Part::Text(text) => {
+                            let node = self.node("[", &text, "]");
+
+                            self.edge(&from, &node, None);
+                            node
+                        }
+                        Part::Token(tok) => self.build(tok, from),
+                    })
+            }
+            Token::Disjunction(tokens) if !tok.is_class() => match tokens.as_slice() {
+                [tok] => self.build(tok, from),
+                tokens => {
+                    let join = self.junction();
+
+                    for tok in tokens {
+                        let end = self.build(tok, from.clone());
+
+                        self.edge(&end, &join, None);
+                    }
+                    join
+                }
+            },
+            Token::GreedyQuantifier(tok, min, max) => self.quantifier(tok, *min, *max, false, from),
+            Token::LazyQuantifier(tok, min, max) => self.quantifier(tok, *min, *max, true, from),
+            Token::Capturing(tok, name) => {
+                self.groups += 1;
+
+                let title = match name {
+                    Some(name) => format!("#{} {name}", self.groups),
+                    None => format!("#{}", self.groups),
+                };
+
+                self.line(&format!("subgraph g{} [{}]", self.groups, quote(&title)));
+                self.depth += 1;
+
+                let end = self.build(tok, from);
+
+                self.depth -= 1;
+                self.line("end");
+                end
+            }
+            Token::Literal(_) => {
+                let node = self.node("[", &tok.to_string(), "]");
+
+                self.edge(&from, &node, None);
+                node
+            }
+            _ => {
+                let node = self.node("([", &tok.to_string(), "])");
+
+                self.edge(&from, &node, None);
+                node
+            }
+        }
+    }
+
+    fn quantifier(
+        &mut self,
+        tok: &Token,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+        from: String,
+    ) -> String {
+        let repeats = max.unwrap_or(2) > 1;
+
+        if min > 0 && !repeats {
+            return self.build(tok, from);
+        }
+
+        let entry = self.junction();
+
+        self.edge(&from, &entry, None);
+
+        let end = self.build(tok, entry.clone());
+        let exit = self.junction();
+
+        self.edge(&end, &exit, None);
+
+        if repeats {
+            let label = match lazy {
+                true => format!("{}, lazy", quantifier_label(min, max, "∞")),
+                false => quantifier_label(min, max, "∞"),
+            };
+
+            self.edge(&exit, &entry, Some(&label));
+        }
+        if min == 0 {
+            self.edge(&entry, &exit, Some("skip"));
+        }
+
+        exit
+    }
+}
+
+/// The expression `tok` as a flowchart.
+pub fn token(tok: &Token) -> String {
+    let mut flowchart = Flowchart {
+        out: String::from("flowchart LR\n"),
+        nodes: 0,
+        groups: 0,
+        depth: 0,
+    };
+
+    flowchart.line("begin(( ))");
+
+    let end = flowchart.build(tok, "begin".to_owned());
+
+    flowchart.line("finish((( )))");
+    flowchart.edge(&end, "finish", None);
+    flowchart.out
+}
+
+fn automaton(transitions: &Transitions) -> String {
+    let mut out = String::from("stateDiagram-v2\n    direction LR\n");
+
+    let _ = writeln!(out, "    [*] --> s{}", transitions.start);
+
+    for (from, label, to) in &transitions.edges {
+        let _ = writeln!(
+            out,
+            "    s{from} --> s{to}: {}",
+            label.replace('#', "#35;").replace(':', "#58;")
+        );
+    }
+
+    for state in &transitions.accepting {
+        let _ = writeln!(out, "    s{state} --> [*]");
+    }
+
+    out
+}
+
+/// Every state of `nfa`.
+pub fn nfa(nfa: &Nfa) -> String {
+    automaton(&Transitions::from(nfa))
+}
+
+/// The live states of `dfa`, see [`Dfa::edges`].
+pub fn dfa(dfa: &Dfa) -> String {
+    automaton(&Transitions::from(dfa))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compiler, parser::parse_expr};
+
+    #[test]
+    fn test_token() {
+        let tok = parse_expr(r"ab(?<x>c|\d)+?".chars()).expect("parse");
+
+        assert_eq!(
+            token(&tok),
+            [
+                "flowchart LR",
+                "    begin(( ))",
+                "    n0[\"ab\"]",
+                "    begin --> n0",
+                "    j1(( ))",
+                "    n0 --> j1",
+                "    subgraph g1 [\"#35;1 x\"]",
+                "        j2(( ))",
+                "        n3[\"c\"]",
+                "        j1 --> n3",
+                "        n3 --> j2",
+                "        n4([\"\\d\"])",
+                "        j1 --> n4",
+                "        n4 --> j2",
+                "    end",
+                "    j5(( ))",
+                "    j2 --> j5",
+                "    j5 -.->|\"1..∞, lazy\"| j1",
+                "    finish((( )))",
+                "    j5 --> finish",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_optional() {
+        let tok = parse_expr("a?".chars()).expect("parse");
+        let flowchart = token(&tok);
+
+        assert!(flowchart.contains("j0 -.->|\"skip\"| j2"));
+        assert!(!flowchart.contains("j2 -.->"));
+    }
+
+    #[test]
+    fn test_dfa() {
+        let dfa = dfa(&compiler::compile(
+            &parse_expr(r"\d+|x".chars()).expect("parse"),
+        ));
+
+        assert_eq!(
+            dfa,
+            [
+                "stateDiagram-v2",
+                "    direction LR",
+                "    [*] --> s0",
+                "    s0 --> s2: \\d",
+                "    s0 --> s3: x",
+                "    s2 --> s2: \\d",
+                "    s2 --> [*]",
+                "    s3 --> [*]",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -1,39 +1,217 @@
-use block::Block;
+use block::{char_width, text_width, Block, RegionKind};
+use glyphs::{Charset, Glyphs};
+use theme::Theme;
 pub mod block;
+pub mod dot;
+pub mod glyphs;
+pub mod html;
+pub mod mermaid;
+pub mod plantuml;
 pub mod style;
+pub mod svg;
+pub mod theme;
 
 use std::cmp::Ordering;
 
-use crate::parser::Token;
+use crate::{
+    ast::{quantifier_suffix, Token},
+    compiler::{Dfa, Nfa},
+    generate::size,
+    trace::Trace,
+};
 
-use self::style::{Color, Format, Style, Styles};
+use self::style::Style;
 
+/// Something that can be drawn into a [`Block`].
 pub trait Styled {
-    fn as_str(&self) -> &str;
-    fn style(&self) -> Option<&Styles>;
+    /// Draws this with its top left corner at `row`, `col` of `block`.
+    fn draw(&self, block: &mut Block, row: usize, col: usize);
 }
 
-impl Styled for str {
-    fn as_str(&self) -> &str {
-        self
+/// What the drawing of a token depends on besides the token.
+struct Context<'a> {
+    glyphs: &'static Glyphs,
+    theme: Theme,
+    /// The number of capturing groups drawn so far.
+    groups: usize,
+    layout: Layout,
+    /// The match whose path is highlighted.
+    trace: Option<&'a Trace>,
+    /// The pre-order number of the token being drawn, in the whole tree.
+    id: usize,
+}
+
+impl Context<'_> {
+    /// What to write about something that happened `count` times in the
+    /// match, such as `×3`.
+    fn times(&self, count: usize) -> String {
+        format!("{}{count}", self.glyphs.times)
     }
+}
+
+/// A row of `width` `fill` characters between `left` and `right`.
+fn track(left: char, fill: char, width: usize, right: char) -> String {
+    format!("{left}{}{right}", fill.to_string().repeat(width))
+}
+
+/// The blocks of the children of a token, their token regions numbered from
+/// that token.
+fn render_children(children: &[Token], cx: &mut Context) -> Vec<Block> {
+    let parent = cx.id;
+    let mut id = 1;
+
+    children
+        .iter()
+        .map(|child| {
+            cx.id = parent + id;
+
+            let mut block = render(child, cx);
+
+            cx.id = parent;
+            block.shift_tokens(id);
+            id += size(child);
+            block
+        })
+        .collect()
+}
+
+/// Whether `ch` is drawn over the previous character rather than in a cell
+/// of its own, such as a combining accent.
+fn is_mark(ch: char) -> bool {
+    char_width(ch) == 0 && escape(ch).is_none()
+}
+
+/// How `ch` is written in a diagram when it would be invisible or break the
+/// layout: controls, whitespace other than the space and the formatting
+/// characters that take no room, escaped like in patterns.
+fn escape(ch: char) -> Option<String> {
+    let escape = match ch {
+        '\n' => "\\n".to_owned(),
+        '\r' => "\\r".to_owned(),
+        '\t' => "\\t".to_owned(),
+        '\u{ad}'
+        | '\u{61c}'
+        | '\u{180e}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{2028}'..='\u{202e}'
+        | '\u{2060}'..='\u{206f}'
+        | '\u{feff}'
+        | '\u{fff9}'..='\u{fffb}'
+        | '\u{e0000}'..='\u{e007f}' => format!("\\u{{{:x}}}", ch as u32),
+        _ if ch.is_control() || (ch.is_whitespace() && ch != ' ') => {
+            format!("\\u{{{:x}}}", ch as u32)
+        }
+        _ => return None,
+    };
 
-    fn style(&self) -> Option<&Styles> {
-        None
+    Some(escape)
+}
+
+/// `ch` as drawn on its own: escaped if needed, and combining marks over a
+/// dotted circle.
+fn glyph(ch: char) -> String {
+    match escape(ch) {
+        Some(escape) => escape,
+        None if is_mark(ch) => format!("◌{ch}"),
+        None => ch.to_string(),
     }
 }
 
-fn render_conjunction(children: &Vec<Token>) -> Block {
-    let child_blocks: Vec<_> = children.into_iter().map(render_token).collect();
-    let (width, height) = child_blocks
+fn render_literal(ch: char, theme: &Theme) -> Block {
+    match escape(ch) {
+        Some(escape) => render_special(&escape, theme.escape),
+        None => render_special(&glyph(ch), theme.literal),
+    }
+}
+
+/// The blocks of the items of a sequence. Combining marks following a
+/// literal are drawn over it, in the same cell, which then stands for both
+/// tokens.
+fn render_sequence(children: &[Token], cx: &mut Context) -> Vec<Block> {
+    let mut child_blocks: Vec<Block> = vec![];
+
+    for (i, child_block) in render_children(children, cx).into_iter().enumerate() {
+        let attached = match (&children[i], i.checked_sub(1).map(|prev| &children[prev])) {
+            (Token::Literal(mark), Some(Token::Literal(base))) => {
+                is_mark(*mark) && escape(*base).is_none()
+            }
+            _ => false,
+        };
+
+        match (attached, child_blocks.last_mut()) {
+            (true, Some(base)) => {
+                if let Token::Literal(mark) = children[i] {
+                    base.cell_mut(0, 0).marks.push(mark);
+                }
+
+                for region in child_block.regions() {
+                    base.add_region(region.kind.clone(), 0, 0, base.width(), 1);
+                }
+            }
+            _ => child_blocks.push(child_block),
+        }
+    }
+
+    child_blocks
+}
+
+/// The blocks of `children` like [`render_sequence`], each run of literals
+/// joined into a single block so that [`render_wrapped`] does not break
+/// lines inside it, unless it is wider than `line_width`: then it is cut
+/// into pieces that fit.
+fn render_words(children: &[Token], line_width: usize, cx: &mut Context) -> Vec<Block> {
+    let parent = cx.id;
+    let mut offset = 0;
+    let mut blocks = vec![];
+
+    for run in children.chunk_by(|a, b| matches!((a, b), (Token::Literal(_), Token::Literal(_)))) {
+        cx.id = parent + offset;
+
+        let mut run_blocks = render_sequence(run, cx);
+
+        cx.id = parent;
+
+        for block in &mut run_blocks {
+            block.shift_tokens(offset);
+        }
+        match run {
+            [Token::Literal(_), _, ..] => {
+                let mut piece: Vec<Block> = vec![];
+
+                for block in run_blocks {
+                    let width: usize = piece.iter().map(Block::width).sum();
+
+                    if !piece.is_empty() && width + block.width() > line_width {
+                        blocks.push(join(&piece));
+                        piece.clear();
+                    }
+                    piece.push(block);
+                }
+                blocks.push(join(&piece));
+            }
+            _ => blocks.extend(run_blocks),
+        }
+        offset += run.iter().map(size).sum::<usize>();
+    }
+
+    blocks
+}
+
+/// Places `blocks` next to each other, their middle rows on the same row.
+fn join(blocks: &[Block]) -> Block {
+    let width = blocks.iter().map(Block::width).sum();
+    let middle = blocks.iter().map(|b| b.height() / 2).max().unwrap_or(0);
+    let height = blocks
         .iter()
-        .fold((0, 0), |(w, h), b| (w + b.width(), h.max(b.height())));
+        .map(|b| middle + b.height() - b.height() / 2)
+        .max()
+        .unwrap_or(0);
 
     let mut new_block = Block::new(width, height);
     let mut col = 0;
 
-    for child_block in child_blocks.iter() {
-        let row = (height - child_block.height()) / 2;
+    for child_block in blocks.iter() {
+        let row = middle - child_block.height() / 2;
         new_block.set(row, col, child_block);
         col += child_block.width();
     }
@@ -41,28 +219,149 @@ fn render_conjunction(children: &Vec<Token>) -> Block {
     new_block
 }
 
-fn render_disjunction_line(idx: usize, len: usize, ordering: Ordering, width: usize) -> String {
-    match ordering {
-        Ordering::Equal if idx == 0 => format!("┬{}┬", "─".repeat(width)),
-        Ordering::Equal if idx == len - 1 => format!("┴{}┴", "─".repeat(width)),
-        Ordering::Equal => format!("┼{}┼", "─".repeat(width)),
-        Ordering::Less if idx == 0 => format!("╭{}╮", "─".repeat(width)),
-        Ordering::Greater if idx == len - 1 => format!("╰{}╯", "─".repeat(width)),
-        _ => format!("├{}┤", "─".repeat(width)),
+fn render_conjunction(children: &[Token], cx: &mut Context) -> Block {
+    join(&render_sequence(children, cx))
+}
+
+/// The columns the tracks on both sides of a wrapped line take.
+const RAILS: usize = 2;
+
+/// Lays a sequence out on as many lines of at most `max_width` cells as
+/// needed. The track leaves every line on the right and comes back on the
+/// left of the next one, through the row between them. An item wider than
+/// a line gets one of its own.
+fn render_wrapped(blocks: Vec<Block>, max_width: usize, glyphs: &Glyphs, style: Style) -> Block {
+    if blocks.iter().map(Block::width).sum::<usize>() <= max_width {
+        return join(&blocks);
+    }
+
+    let mut lines: Vec<Vec<Block>> = vec![];
+    let mut line_width = 0;
+
+    for block in blocks {
+        match lines.last_mut() {
+            Some(line) if line_width + block.width() + RAILS <= max_width => {
+                line_width += block.width();
+                line.push(block);
+            }
+            _ => {
+                line_width = block.width();
+                lines.push(vec![block]);
+            }
+        }
+    }
+
+    let lines: Vec<Block> = lines.iter().map(|line| join(line)).collect();
+    let width = lines.iter().map(Block::width).max().unwrap_or(0) + RAILS;
+    let height = lines.iter().map(Block::height).sum::<usize>() + lines.len() - 1;
+    let mut new_block = Block::new(width, height);
+    let mut row = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let middle = row + line.height() / 2;
+        let last = i == lines.len() - 1;
+
+        if i == 0 {
+            new_block.set_styled(middle, 0, &glyphs.horizontal.to_string(), style);
+        } else {
+            let connector = track(
+                glyphs.top_left,
+                glyphs.horizontal,
+                width - RAILS,
+                glyphs.bottom_right,
+            );
+
+            new_block.set_styled(row - 1, 0, &connector, style);
+
+            for r in row..middle {
+                new_block.set_styled(r, 0, &glyphs.vertical.to_string(), style);
+            }
+
+            new_block.set_styled(middle, 0, &glyphs.bottom_left.to_string(), style);
+        }
+
+        let exit = if last {
+            glyphs.horizontal
+        } else {
+            glyphs.top_right
+        };
+        let fill = format!(
+            "{}{exit}",
+            glyphs
+                .horizontal
+                .to_string()
+                .repeat(width - RAILS - line.width())
+        );
+
+        new_block.set_styled(middle, 1 + line.width(), &fill, style);
+
+        if !last {
+            for r in middle + 1..row + line.height() {
+                new_block.set_styled(r, width - 1, &glyphs.vertical.to_string(), style);
+            }
+        }
+
+        new_block.set(row, 1, line);
+        row += line.height() + 1;
     }
+
+    new_block
+}
+
+fn render_disjunction_line(
+    idx: usize,
+    len: usize,
+    ordering: Ordering,
+    width: usize,
+    g: &Glyphs,
+) -> String {
+    let (left, right) = match ordering {
+        Ordering::Equal if idx == 0 => (g.tee_down, g.tee_down),
+        Ordering::Equal if idx == len - 1 => (g.tee_up, g.tee_up),
+        Ordering::Equal => (g.cross, g.cross),
+        Ordering::Less if idx == 0 => (g.top_left, g.top_right),
+        Ordering::Greater if idx == len - 1 => (g.bottom_left, g.bottom_right),
+        _ => (g.tee_right, g.tee_left),
+    };
+
+    track(left, g.horizontal, width, right)
 }
 
-fn render_disjunction(children: &Vec<Token>) -> Block {
-    if children.len() == 1 {
-        return render_token(children.iter().next().unwrap());
+/// Draws the alternatives below each other, between rails drawn with
+/// `style`. The alternatives a match took are checked, with the number of
+/// times when more than once, and their way through the rails highlighted.
+fn render_disjunction(children: &[Token], cx: &mut Context, style: Style) -> Block {
+    let g = cx.glyphs;
+    let taken = cx
+        .trace
+        .map(|trace| trace.alternatives(cx.id))
+        .unwrap_or_default();
+    let annotations: Vec<String> = (0..children.len())
+        .map(
+            |i| match taken.iter().filter(|taken| **taken == i).count() {
+                0 => String::new(),
+                1 => g.check.to_string(),
+                count => format!("{}{}", g.check, cx.times(count)),
+            },
+        )
+        .collect();
+    let mut child_blocks = render_children(children, cx);
+
+    if child_blocks.len() == 1 {
+        return child_blocks.remove(0);
     }
 
-    let child_blocks: Vec<_> = children.into_iter().map(render_token).collect();
     let (width, height) = child_blocks
         .iter()
-        .fold((0, 0), |(w, h), b| (w.max(b.width()), h + b.height()));
+        .zip(&annotations)
+        .fold((0, 0), |(w, h), (b, annotation)| {
+            (w.max(b.width() + text_width(annotation)), h + b.height())
+        });
 
-    let height = (height + 1) % 2 + height;
+    let height = match cx.layout {
+        Layout::Expanded => (height + 1) % 2 + height,
+        Layout::Compact => height,
+    };
     let middle = height / 2;
 
     let mut new_block = Block::new(width + 2, height);
@@ -70,53 +369,123 @@ fn render_disjunction(children: &Vec<Token>) -> Block {
 
     if child_blocks.len() >= 2 {
         let last = child_blocks.last().unwrap();
-        let first_middle = child_blocks.get(0).unwrap().height() / 2;
+        let first_middle = child_blocks[0].height() / 2;
         let last_middle = last.height() / 2
             + child_blocks.iter().fold(0, |sum, b| sum + b.height())
             - last.height() / 2
             - 1;
 
         for r in first_middle..last_middle {
-            new_block.set(r, 0, format!("│{}│", " ".repeat(width)).as_str());
+            new_block.set_styled(r, 0, &track(g.vertical, ' ', width, g.vertical), style);
         }
     }
 
-    new_block.set(middle, 0, format!("┤{}├", " ".repeat(width)).as_str());
+    new_block.set_styled(
+        middle,
+        0,
+        &track(g.tee_left, ' ', width, g.tee_right),
+        style,
+    );
 
     for (i, child_block) in child_blocks.iter().enumerate() {
-        let child_middle = child_block.height() / 2;
+        let line = row + child_block.height() / 2;
 
-        new_block.set(
-            row + child_middle,
+        new_block.set_styled(
+            line,
             0,
-            render_disjunction_line(
-                i,
-                child_blocks.len(),
-                (row + child_middle).cmp(&middle),
-                width,
-            )
-            .as_str(),
+            &render_disjunction_line(i, child_blocks.len(), line.cmp(&middle), width, g),
+            style,
         );
 
+        if !annotations[i].is_empty() {
+            new_block.set_styled(line, 1 + child_block.width(), &annotations[i], style);
+        }
+
         new_block.set(row, 1, child_block);
         row += child_block.height();
     }
 
+    if let Some(path) = cx.trace.map(|_| cx.theme.path) {
+        let mut row = 0;
+
+        for (child_block, annotation) in child_blocks.iter().zip(&annotations) {
+            let line = row + child_block.height() / 2;
+
+            if !annotation.is_empty() {
+                new_block.apply_style(line, 0, 1, path);
+                new_block.apply_style(
+                    line,
+                    1 + child_block.width(),
+                    width - child_block.width() + 1,
+                    path,
+                );
+
+                for r in line.min(middle)..=line.max(middle) {
+                    new_block.apply_style(r, 0, 1, path);
+                    new_block.apply_style(r, width + 1, 1, path);
+                }
+            }
+            row += child_block.height();
+        }
+    }
+
     new_block
 }
 
-fn render_quantifier(tok: &Token, min: usize, max: Option<usize>) -> Block {
-    let label = match max {
+/// The bounds of a quantifier as written under its loop, empty for `?`.
+/// `infinity` stands for the missing upper bound.
+fn quantifier_label(min: usize, max: Option<usize>, infinity: &str) -> String {
+    match max {
         Some(1) if min == 0 => "".to_owned(),
         Some(max) if max == min => format!("={min}"),
         Some(max) if min == 0 => format!("..={max}"),
         Some(max) => format!("{min}..={max}"),
-        None if min == 0 => format!(".."),
-        None => format!("{min}.."),
+        None => format!("{min}..{infinity}"),
+    }
+}
+
+/// Draws the loop of a quantifier below its token and the way around it
+/// above. Lazy quantifiers have both dashed and a `?` after their bounds.
+/// In a match, the number of times the token ran follows the bounds, and
+/// the tracks taken are highlighted.
+fn render_quantifier(
+    tok: &Token,
+    min: usize,
+    max: Option<usize>,
+    lazy: bool,
+    cx: &mut Context,
+) -> Block {
+    let (g, style) = (cx.glyphs, cx.theme.quantifier);
+    let label = quantifier_label(min, max, g.infinity);
+    let (label, loop_track) = match lazy {
+        true => (label + "?", g.dashed),
+        false => (label, g.horizontal),
+    };
+    let repeats = cx
+        .trace
+        .map(|trace| trace.repeats(cx.id))
+        .unwrap_or_default();
+    let counts = match repeats.is_empty() {
+        true => String::new(),
+        false => repeats
+            .iter()
+            .map(|count| cx.times(*count))
+            .collect::<Vec<_>>()
+            .join(","),
+    };
+    let label = match (label.is_empty(), counts.is_empty()) {
+        (_, true) => label,
+        (true, false) => counts.clone(),
+        (false, false) => format!("{label} {counts}"),
     };
+    let block = render_children(std::slice::from_ref(tok), cx).remove(0);
 
-    let block = render_token(tok);
-    let min_width = label.chars().count().max(2);
+    if cx.layout == Layout::Compact && !matches!(tok, Token::Conjunction(items) if items.len() > 1)
+    {
+        return render_suffixed(&block, &quantifier_suffix(min, max, lazy), &counts, cx);
+    }
+
+    let min_width = text_width(&label).max(2);
     let zero = min == 0;
     let more_than_one = max.unwrap_or(2) > 1;
     let width = block.width().max(min_width);
@@ -124,73 +493,467 @@ fn render_quantifier(tok: &Token, min: usize, max: Option<usize>) -> Block {
 
     if zero {
         for i in 2..new_block.height() / 2 {
-            new_block.set(i, 0, format!("│{}│", " ".repeat(width)).as_str());
+            new_block.set_styled(i, 0, &track(g.vertical, ' ', width, g.vertical), style);
         }
 
-        new_block.set(1, 0, format!("╭{}╮", "─".repeat(width)).as_str());
-        new_block.set(
+        new_block.set_styled(
+            1,
+            0,
+            &track(g.top_left, loop_track, width, g.top_right),
+            style,
+        );
+        new_block.set_styled(
             new_block.height() / 2,
             0,
-            format!("┴{}┴", "─".repeat(width)).as_str(),
+            &track(g.tee_up, g.horizontal, width, g.tee_up),
+            style,
         );
     } else {
-        new_block.set(
+        new_block.set_styled(
             new_block.height() / 2,
             0,
-            format!("─{}─", "─".repeat(width)).as_str(),
+            &track(g.horizontal, g.horizontal, width, g.horizontal),
+            style,
         );
     }
 
     if more_than_one {
-        new_block.set(
+        new_block.set_styled(
             block.height() + 2,
             1,
-            format!("╰{}╯", "─".repeat(width - 2)).as_str(),
+            &track(g.bottom_left, loop_track, width - 2, g.bottom_right),
+            style,
+        );
+    }
+
+    if more_than_one || !counts.is_empty() {
+        new_block.set_styled(block.height() + 3, 1, &label, style);
+        new_block.add_region(
+            RegionKind::Label,
+            block.height() + 3,
+            1,
+            text_width(&label),
+            1,
         );
-        new_block.set(block.height() + 3, 1, label.as_str());
     }
 
     new_block.set(2, 1, &block);
+
+    if let Some(path) = cx.trace.map(|_| cx.theme.path) {
+        let middle = new_block.height() / 2;
+
+        if repeats.iter().any(|count| *count > 0) {
+            new_block.apply_style(middle, 0, 1, path);
+            new_block.apply_style(middle, 1 + block.width(), width - block.width() + 1, path);
+        }
+        if repeats.contains(&0) {
+            new_block.apply_style(1, 0, width + 2, path);
+
+            for row in 2..=middle {
+                new_block.apply_style(row, 0, 1, path);
+                new_block.apply_style(row, width + 1, 1, path);
+            }
+        }
+        if repeats.iter().any(|count| *count > 1) {
+            new_block.apply_style(block.height() + 2, 1, width, path);
+        }
+        if !counts.is_empty() {
+            let counts_width = text_width(&counts);
+
+            new_block.apply_style(
+                block.height() + 3,
+                1 + text_width(&label) - counts_width,
+                counts_width,
+                path,
+            );
+        }
+    }
+
     new_block
 }
 
-fn render_special(s: &str) -> Block {
-    let mut b = Block::from(s);
+/// Writes the suffix of a quantifier after the block of its token, on its
+/// middle row, followed by the number of times it ran in a match.
+fn render_suffixed(block: &Block, suffix: &str, counts: &str, cx: &Context) -> Block {
+    let label = match counts.is_empty() {
+        true => suffix.to_owned(),
+        false => format!("{suffix} {counts}"),
+    };
+    let middle = block.height() / 2;
+    let mut new_block = Block::new(block.width() + text_width(&label), block.height());
 
-    b.with_styles(|styles| {
-        styles.clear(Style {
-            foreground: Some(Color::Blue),
-            format: Some(Format::Bold),
-            ..Default::default()
-        })
-    });
+    new_block.set(0, 0, block);
+    new_block.set_styled(middle, block.width(), &label, cx.theme.quantifier);
+    new_block.add_region(
+        RegionKind::Label,
+        middle,
+        block.width(),
+        text_width(&label),
+        1,
+    );
+
+    if !counts.is_empty() {
+        let counts_width = text_width(counts);
+
+        new_block.apply_style(
+            middle,
+            new_block.width() - counts_width,
+            counts_width,
+            cx.theme.path,
+        );
+    }
+
+    new_block
+}
+
+/// The characters an alternation of single characters stands for, such as
+/// `a|b|[cd]`, as the items of a class. `None` for other tokens.
+fn class_items(tok: &Token) -> Option<Vec<Token>> {
+    let Token::Disjunction(alternatives) = tok else {
+        return None;
+    };
+    let mut items = vec![];
+
+    for alternative in alternatives {
+        // The parser wraps every alternative in a conjunction.
+        let alternative = match alternative {
+            Token::Conjunction(tokens) if tokens.len() == 1 => &tokens[0],
+            alternative => alternative,
+        };
+
+        match alternative {
+            Token::Disjunction(_) if alternative.is_class() => {
+                items.extend(alternative.children().iter().cloned())
+            }
+            _ if alternative.is_class_item() => items.push(alternative.clone()),
+            _ => return None,
+        }
+    }
+
+    Some(items)
+}
+
+/// Draws an alternation of single characters as one box, written like a
+/// class. The tokens inside it all stand for the whole box.
+fn render_class_box(tok: &Token, items: Vec<Token>, cx: &Context) -> Block {
+    let mut block = render_special(&Token::Disjunction(items).to_string(), cx.theme.class);
+    let on_path = cx
+        .trace
+        .is_some_and(|trace| !trace.alternatives(cx.id).is_empty() || trace.is_matched(cx.id));
+
+    for id in 1..size(tok) {
+        block.add_region(RegionKind::Token(id), 0, 0, block.width(), 1);
+    }
+    if on_path {
+        block.apply_style(0, 0, block.width(), cx.theme.path);
+    }
+    block.add_outer_region(RegionKind::Class);
+    block
+}
 
+fn render_special(s: &str, style: Style) -> Block {
+    let mut b = Block::from(s);
+
+    b.set_style(style);
     b
 }
 
+/// Frames the block of a capturing group, with its number and name on the
+/// top border. The track crosses the sides on the middle row. In a match,
+/// what the group captured follows its name, and the track through the frame
+/// is highlighted with the `path` style that comes with it.
+fn render_group(
+    block: &Block,
+    number: usize,
+    name: Option<&str>,
+    capture: Option<(&str, Style)>,
+    g: &Glyphs,
+    style: Style,
+) -> Block {
+    let captured = capture
+        .map(|(text, _)| format!("{text:?} "))
+        .unwrap_or_default();
+    let label = match name {
+        Some(name) => format!(" #{number} {name} {captured}"),
+        None => format!(" #{number} {captured}"),
+    };
+    let width = block.width().max(text_width(&label));
+    let height = block.height().max(1);
+    let middle = height / 2 + 1;
+    let mut new_block = Block::new(width + 2, height + 2);
+
+    new_block.set_styled(
+        0,
+        0,
+        &track(g.frame_top_left, g.horizontal, width, g.frame_top_right),
+        style,
+    );
+    new_block.set(0, 1, label.as_str());
+
+    for row in 1..=height {
+        new_block.set_styled(row, 0, &track(g.vertical, ' ', width, g.vertical), style);
+    }
+
+    new_block.set_styled(
+        middle,
+        0,
+        &track(g.cross, g.horizontal, width, g.cross),
+        style,
+    );
+    new_block.set_styled(
+        height + 1,
+        0,
+        &track(
+            g.frame_bottom_left,
+            g.horizontal,
+            width,
+            g.frame_bottom_right,
+        ),
+        style,
+    );
+    let col = 1 + (width - block.width()) / 2;
+
+    new_block.set(middle - block.height() / 2, col, block);
+
+    if let Some((_, path)) = capture {
+        let captured_width = text_width(&captured) - 1;
+
+        new_block.apply_style(0, text_width(&label) - captured_width, captured_width, path);
+        new_block.apply_style(middle, 0, col, path);
+        new_block.apply_style(
+            middle,
+            col + block.width(),
+            width + 1 - block.width() - (col - 1),
+            path,
+        );
+    }
+
+    new_block
+}
+
+/// How much room diagrams take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Loops and bypasses around quantified tokens, with their bounds below,
+    /// and every alternative on its own line.
+    #[default]
+    Expanded,
+    /// Quantifiers written after their token like in patterns, alternations
+    /// of single characters in one box like classes, and no padding rows.
+    Compact,
+}
+
+/// How diagrams are laid out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The number of columns a diagram should fit in. A sequence at the top
+    /// of the expression is wrapped onto several lines to fit, joined by
+    /// tracks going back to the left; nothing else is.
+    pub max_width: Option<usize>,
+    /// The characters tracks and frames are drawn with.
+    pub charset: Charset,
+    /// Whether quantifiers and alternations are drawn in full or compactly.
+    pub layout: Layout,
+    /// The styles of the parts of the diagram.
+    pub theme: Theme,
+    /// A match to draw the path of, see [`crate::trace`].
+    pub trace: Option<Trace>,
+}
+
+/// Draws `tok`, with a [`RegionKind::Token`] region for every token of the
+/// tree.
 pub fn render_token(tok: &Token) -> Block {
-    match tok {
-        Token::Literal(ch) => Block::from(format!("{ch}").as_str()),
-        Token::Start => render_special("^"),
-        Token::End => render_special("$"),
-        Token::Alphanumeric => render_special("\\w"),
-        Token::NotAlphanumeric => render_special("\\W"),
-        Token::Digit => render_special("\\d"),
-        Token::NotDigit => render_special("\\D"),
-        Token::Whitespace => render_special("\\s"),
-        Token::NotWhitespace => render_special("\\S"),
-        Token::WordBoundary => render_special("\\b"),
-        Token::Any => render_special("."),
-        Token::Conjunction(tokens) => render_conjunction(tokens),
-        Token::Disjunction(tokens) => render_disjunction(tokens),
-        Token::LazyQuantifier(tok, min, max) => render_quantifier(tok, *min, *max),
-        Token::GreedyQuantifier(tok, min, max) => render_quantifier(tok, *min, *max),
-        Token::Capturing(tok, _) => render_token(tok),
-        Token::AsciiRange(start, end) => Block::from(format!("{start}-{end}").as_str()),
+    render_token_with(tok, &Options::default())
+}
+
+/// Draws `tok` laid out as set by `options`, see [`render_token`].
+pub fn render_token_with(tok: &Token, options: &Options) -> Block {
+    let mut cx = Context {
+        glyphs: options.charset.glyphs(),
+        theme: options.theme,
+        groups: 0,
+        layout: options.layout,
+        trace: options.trace.as_ref(),
+        id: 0,
+    };
+    let mut block = match (tok, options.max_width) {
+        (Token::Conjunction(children), Some(max_width)) => {
+            let mut rail = cx.theme.rail;
+
+            if cx.trace.is_some() {
+                rail.apply(&cx.theme.path);
+            }
+            let line_width = max_width.saturating_sub(RAILS).max(1);
+
+            render_wrapped(
+                render_words(children, line_width, &mut cx),
+                max_width,
+                cx.glyphs,
+                rail,
+            )
+        }
+        _ => return render(tok, &mut cx),
+    };
+
+    block.add_outer_region(RegionKind::Token(0));
+    block
+}
+
+fn render(tok: &Token, cx: &mut Context) -> Block {
+    let mut block = match tok {
+        Token::Literal(ch) => render_literal(*ch, &cx.theme),
+        Token::Start => render_special("^", cx.theme.anchor),
+        Token::End => render_special("$", cx.theme.anchor),
+        Token::Alphanumeric => render_special("\\w", cx.theme.class),
+        Token::NotAlphanumeric => render_special("\\W", cx.theme.class),
+        Token::Digit => render_special("\\d", cx.theme.class),
+        Token::NotDigit => render_special("\\D", cx.theme.class),
+        Token::Whitespace => render_special("\\s", cx.theme.class),
+        Token::NotWhitespace => render_special("\\S", cx.theme.class),
+        Token::WordBoundary => render_special("\\b", cx.theme.anchor),
+        Token::Any => render_special(".", cx.theme.class),
+        Token::Conjunction(tokens) => render_conjunction(tokens, cx),
+        Token::Disjunction(_) if cx.layout == Layout::Compact && class_items(tok).is_some() => {
+            render_class_box(tok, class_items(tok).unwrap_or_default(), cx)
+        }
+        Token::Disjunction(tokens) if tok.is_class() => {
+            let mut block = render_disjunction(tokens, cx, cx.theme.class);
+            block.add_outer_region(RegionKind::Class);
+            block
+        }
+        Token::Disjunction(tokens) => render_disjunction(tokens, cx, cx.theme.rail),
+        Token::LazyQuantifier(tok, min, max) => render_quantifier(tok, *min, *max, true, cx),
+        Token::GreedyQuantifier(tok, min, max) => render_quantifier(tok, *min, *max, false, cx),
+        Token::Capturing(tok, name) => {
+            // Numbered before the groups inside it, like PCRE does.
+            cx.groups += 1;
+
+            let number = cx.groups;
+            let captured = cx.trace.and_then(|trace| {
+                trace
+                    .capture(cx.id)
+                    .map(|(start, end)| trace.text(start, end))
+            });
+            let inner = render_children(std::slice::from_ref(tok), cx).remove(0);
+            let capture = captured.as_deref().map(|text| (text, cx.theme.path));
+            let mut block = render_group(
+                &inner,
+                number,
+                name.as_deref(),
+                capture,
+                cx.glyphs,
+                cx.theme.group,
+            );
+
+            block.add_outer_region(RegionKind::Group(number, name.clone()));
+            block
+        }
+        Token::AsciiRange(start, end) => render_special(
+            &format!("{}-{}", glyph(*start), glyph(*end)),
+            cx.theme.class,
+        ),
+    };
+
+    if cx.trace.is_some_and(|trace| trace.is_matched(cx.id)) {
+        for row in 0..block.height() {
+            block.apply_style(row, 0, block.width(), cx.theme.path);
+        }
     }
+
+    block.add_outer_region(RegionKind::Token(0));
+    block
+}
+
+/// A part of a sequence, for the exporters that draw a run of literals as a
+/// single node.
+enum Part<'a> {
+    /// Literals, as written in a pattern.
+    Text(String),
+    Token(&'a Token),
+}
+
+fn parts(tokens: &[Token]) -> Vec<Part<'_>> {
+    let mut parts = vec![];
+
+    for tok in tokens {
+        match (tok, parts.last_mut()) {
+            (Token::Literal(_), Some(Part::Text(text))) => text.push_str(&tok.to_string()),
+            (Token::Literal(_), _) => parts.push(Part::Text(tok.to_string())),
+            _ => parts.push(Part::Token(tok)),
+        }
+    }
+
+    parts
+}
+
+/// An automaton as listed by the exporters: its start state, the states
+/// accepting the input and the labeled transitions.
+struct Transitions {
+    start: usize,
+    accepting: Vec<usize>,
+    edges: Vec<(usize, String, usize)>,
+}
+
+impl From<&Nfa> for Transitions {
+    fn from(nfa: &Nfa) -> Self {
+        let edges = nfa
+            .states()
+            .iter()
+            .enumerate()
+            .flat_map(|(from, state)| {
+                state
+                    .transitions
+                    .iter()
+                    .map(move |(edge, to)| (from, edge.label(), *to))
+            })
+            .collect();
+
+        Self {
+            start: nfa.start(),
+            accepting: vec![nfa.accept()],
+            edges,
+        }
+    }
+}
+
+/// Only the live states, see [`Dfa::edges`].
+impl From<&Dfa> for Transitions {
+    fn from(dfa: &Dfa) -> Self {
+        let alive = dfa.live_states();
+
+        Self {
+            start: dfa.start(),
+            accepting: (0..dfa.state_count())
+                .filter(|state| alive[*state] && dfa.is_accepting(*state))
+                .collect(),
+            edges: dfa
+                .edges()
+                .into_iter()
+                .map(|(from, label, to)| (from, label.to_string(), to))
+                .collect(),
+        }
+    }
+}
+
+/// Renders two expressions next to each other, separated by a vertical rule,
+/// each drawn as set by `options`.
+pub fn render_side_by_side(left: &Token, right: &Token, options: &Options) -> Block {
+    let left = render_token_with(left, options);
+    let right = render_token_with(right, options);
+    let height = left.height().max(right.height());
+    let mut new_block = Block::new(left.width() + right.width() + 3, height);
+
+    for row in 0..height {
+        new_block.set(row, left.width(), " │ ");
+    }
+
+    new_block.set((height - left.height()) / 2, 0, &left);
+    new_block.set((height - right.height()) / 2, left.width() + 3, &right);
+    new_block
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests {
     use super::*;
 
@@ -210,7 +973,7 @@ mod tests {
         b2.set(2, 1, &b1);
 
         assert_eq!(
-            b2.as_ref(),
+            b2.to_string(),
             vec![
                 "          ",
                 "          ",
@@ -229,7 +992,7 @@ mod tests {
 
         assert_eq!(b.width(), 1);
         assert_eq!(b.height(), 1);
-        assert_eq!(b.as_str(), "a\n");
+        assert_eq!(b.to_string(), "a\n");
     }
 
     #[test]
@@ -244,7 +1007,7 @@ mod tests {
 
         assert_eq!(b.width(), 5);
         assert_eq!(b.height(), 1);
-        assert_eq!(b.as_str(), "hello\n");
+        assert_eq!(b.to_string(), "hello\n");
     }
 
     #[test]
@@ -262,8 +1025,8 @@ mod tests {
         ]));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "╭hello╮", //
                 "┼a────┼",
                 "╰\\s───╯",
@@ -287,8 +1050,8 @@ mod tests {
         ]));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "╭hello╮", //
                 "┴a────┴",
                 "       ",
@@ -313,13 +1076,13 @@ mod tests {
         ));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "       ",
-                "╭─────╮", //
+                "╭╌╌╌╌╌╮", //
                 "┴hello┴",
-                " ╰───╯ ",
-                " ..    ",
+                " ╰╌╌╌╯ ",
+                " 0..∞? ",
                 "",
             ]
             .join("\n")
@@ -341,13 +1104,13 @@ mod tests {
         ));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "       ",
-                "╭─────╮", //
+                "╭╌╌╌╌╌╮", //
                 "┴hello┴",
-                " ╰───╯ ",
-                " ..=2  ",
+                " ╰╌╌╌╯ ",
+                " ..=2? ",
                 "",
             ]
             .join("\n")
@@ -369,13 +1132,13 @@ mod tests {
         ));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "       ", //
                 "       ",
                 "─hello─",
-                " ╰───╯ ",
-                " 1..   ",
+                " ╰╌╌╌╯ ",
+                " 1..∞? ",
                 "",
             ]
             .join("\n")
@@ -397,10 +1160,10 @@ mod tests {
         ));
 
         assert_eq!(
-            b.as_str(),
-            &vec![
+            b.to_string(),
+            vec![
                 "       ",
-                "╭─────╮", //
+                "╭╌╌╌╌╌╮", //
                 "┴hello┴",
                 "       ",
                 "       ",
@@ -409,4 +1172,352 @@ mod tests {
             .join("\n")
         );
     }
+
+    #[test]
+    fn test_greedy_quantifier() {
+        let b = render_token(&Token::GreedyQuantifier(
+            Box::new(Token::Literal('a')),
+            0,
+            None,
+        ));
+
+        assert_eq!(
+            b.to_string(),
+            vec![
+                "      ",
+                "╭────╮", //
+                "┴a───┴",
+                " ╰──╯ ",
+                " 0..∞ ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let b = render_token(&Token::Conjunction(vec![
+            Token::Capturing(
+                Box::new(Token::Capturing(Box::new(Token::Literal('a')), None)),
+                Some("year".to_owned()),
+            ),
+            Token::Capturing(Box::new(Token::Conjunction(vec![])), None),
+        ]));
+
+        assert_eq!(
+            b.to_string(),
+            vec![
+                "┌ #1 year ┐      ",
+                "│ ┌ #2 ┐  │┌ #3 ┐",
+                "┼─┼─a──┼──┼┼────┼",
+                "│ └────┘  │└────┘",
+                "└─────────┘      ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let b = render_token(&Token::Disjunction(vec![
+            Token::Conjunction(vec![Token::Literal('日'), Token::Literal('本')]),
+            Token::Literal('a'),
+        ]));
+
+        assert_eq!(b.width(), 6);
+        assert_eq!(
+            b.to_string(),
+            vec![
+                "╭日本╮", //
+                "┴a───┴",
+                "      ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_combining_marks() {
+        let b = render_token(&Token::Conjunction(vec![
+            Token::Literal('e'),
+            Token::Literal('\u{301}'),
+            Token::Literal('x'),
+        ]));
+
+        assert_eq!(b.width(), 2);
+        assert_eq!(b.to_string(), "e\u{301}x\n");
+        // The accent stands for its own token too, in the cell of the `e`.
+        assert!(b
+            .regions()
+            .iter()
+            .any(|region| region.kind == RegionKind::Token(2) && region.col == 0));
+
+        let b = render_token(&Token::Literal('\u{301}'));
+
+        assert_eq!(b.width(), 1);
+        assert_eq!(b.to_string(), "◌\u{301}\n");
+    }
+
+    #[test]
+    fn test_escaped_characters() {
+        let b = render_token(&Token::Conjunction(vec![
+            Token::Literal('\t'),
+            Token::Literal('\u{200b}'),
+            Token::AsciiRange('\n', '\r'),
+        ]));
+
+        assert_eq!(b.to_string(), "\\t\\u{200b}\\n-\\r\n");
+        assert_eq!(b.width(), 15);
+    }
+
+    #[test]
+    fn test_wrapped() {
+        let tok = crate::parser::parse_expr("abc(?:d|e)fgh".chars()).expect("parse");
+        let options = Options {
+            max_width: Some(8),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_token_with(&tok, &options).to_string(),
+            [
+                "    ╭d╮ ", //
+                "─abc┴e┴╮",
+                "       │",
+                "╭──────╯",
+                "╰fgh────",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_wrapped_words() {
+        let tok = crate::parser::parse_expr("ab(?:c|d)efgh".chars()).expect("parse");
+        let options = Options {
+            max_width: Some(8),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_token_with(&tok, &options).to_string(),
+            [
+                "   ╭c╮ ", //
+                "─ab┴d┴╮",
+                "      │",
+                "╭─────╯",
+                "╰efgh──",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_wrapped_long_words() {
+        let tok = crate::parser::parse_expr("abcdefghijklmnopqrstuvwxyz(?:0|1)abc".chars())
+            .expect("parse");
+
+        for max_width in [6, 10, 30] {
+            let options = Options {
+                max_width: Some(max_width),
+                ..Default::default()
+            };
+            let render = render_token_with(&tok, &options).to_string();
+
+            assert!(
+                render.lines().all(|row| text_width(row) <= max_width),
+                "{render}"
+            );
+            assert!(render.contains("abcd"));
+        }
+    }
+
+    #[test]
+    fn test_wrapped_fits() {
+        let tok = crate::parser::parse_expr("abc".chars()).expect("parse");
+
+        assert_eq!(
+            render_token_with(
+                &tok,
+                &Options {
+                    max_width: Some(3),
+                    ..Default::default()
+                }
+            )
+            .to_string(),
+            "abc\n"
+        );
+    }
+
+    #[test]
+    fn test_charsets() {
+        let tok = crate::parser::parse_expr("(a|bc)+?".chars()).expect("parse");
+        let render = |charset| {
+            render_token_with(
+                &tok,
+                &Options {
+                    charset,
+                    ..Default::default()
+                },
+            )
+            .to_string()
+        };
+
+        assert_eq!(
+            render(Charset::Ascii),
+            [
+                "        ", //
+                "        ", " + #1 + ", " |+a-+| ", "-++bc++-", " |    | ", " +----+ ", " +~~~~+ ",
+                " 1..*?  ", "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(Charset::Square),
+            [
+                "        ", //
+                "        ",
+                " ┌ #1 ┐ ",
+                " │┌a─┐│ ",
+                "─┼┴bc┴┼─",
+                " │    │ ",
+                " └────┘ ",
+                " └╌╌╌╌┘ ",
+                " 1..∞?  ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_theme_styles() {
+        let tok = crate::parser::parse_expr(r"^(a|b|c|[\d])*\t".chars()).expect("parse");
+        let b = render_token(&tok);
+        let theme = Theme::default();
+        let style_of = |ch: char| {
+            b.rows()
+                .flatten()
+                .find(|cell| cell.ch == ch)
+                .map(|cell| cell.style)
+                .expect("drawn")
+        };
+
+        assert_eq!(style_of('^'), theme.anchor);
+        assert_eq!(style_of('a'), theme.literal);
+        assert_eq!(style_of('d'), theme.class);
+        assert_eq!(style_of('t'), theme.escape);
+        assert_eq!(style_of('┌'), theme.group);
+        assert_eq!(style_of('∞'), theme.quantifier);
+        assert_eq!(style_of('╭'), theme.quantifier);
+        assert_eq!(style_of('├'), theme.rail);
+    }
+
+    #[test]
+    fn test_trace() {
+        let tok = crate::parser::parse_expr("(a|bc)+d?".chars()).expect("parse");
+        let options = Options {
+            trace: crate::trace::trace(&tok, "xabc").expect("within the step limit"),
+            ..Default::default()
+        };
+        let b = render_token_with(&tok, &options);
+        let path = options.theme.path;
+
+        assert_eq!(
+            b.to_string(),
+            vec![
+                "                 ",
+                "                 ",
+                " ┌ #1 \"bc\" ┐     ",
+                " │  ╭a✓─╮  │ ╭──╮",
+                "─┼──┴bc✓┴──┼─┴d─┴",
+                " │         │     ",
+                " └─────────┘  ×0 ",
+                " ╰─────────╯     ",
+                " 1..∞ ×2         ",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let styled = |row: usize, col: usize| {
+            let mut style = b.rows().nth(row).expect("row")[col].style;
+            let before = style;
+
+            style.apply(&path);
+            style == before
+        };
+
+        // Both alternatives, the loop, the bypass of `d?` and what is
+        // written about them are on the path; `d` and its track are not.
+        assert!(styled(3, 5) && styled(4, 5) && styled(4, 0) && styled(7, 4));
+        assert!(styled(3, 13) && styled(2, 6) && styled(8, 6) && styled(6, 14));
+        assert!(!styled(4, 14) && !styled(4, 15) && !styled(6, 3));
+    }
+
+    #[test]
+    fn test_compact() {
+        let tok =
+            crate::parser::parse_expr(r"^(\d{4}|a|b|[cd])-(?:ab|c)*?$".chars()).expect("parse");
+        let options = Options {
+            layout: Layout::Compact,
+            ..Default::default()
+        };
+        let b = render_token_with(&tok, &options);
+
+        assert_eq!(
+            b.to_string(),
+            vec![
+                " ┌ #1 ───┐        ",
+                " │╭\\d{4}╮│        ",
+                " │├a────┤│ ╭ab╮   ",
+                "^┼┼b────┼┼-┴c─┴*?$",
+                " │╰[cd]─╯│        ",
+                " └───────┘        ",
+                "",
+            ]
+            .join("\n")
+        );
+        // The letters all stand for the box they were merged into.
+        assert!(b
+            .regions()
+            .iter()
+            .any(|region| region.kind == RegionKind::Class && region.width == 4));
+    }
+
+    #[test]
+    fn test_compact_class() {
+        let tok = crate::parser::parse_expr("x|y|[0-9]".chars()).expect("parse");
+        let options = Options {
+            layout: Layout::Compact,
+            ..Default::default()
+        };
+
+        assert_eq!(render_token_with(&tok, &options).to_string(), "[xy0-9]\n");
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let b = render_side_by_side(
+            &Token::Conjunction(vec![Token::Literal('a'), Token::Literal('b')]),
+            &Token::Disjunction(vec![Token::Literal('c'), Token::Literal('d')]),
+            &Options::default(),
+        );
+
+        assert_eq!(
+            b.to_string(),
+            vec![
+                "   │ ╭c╮", //
+                "ab │ ┴d┴",
+                "   │    ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
 }
@@ -0,0 +1,224 @@
+//! PlantUML output of diagrams and automata.
+//!
+//! Expressions become activity diagrams: alternatives are `if` branches,
+//! quantifiers are `repeat` loops with their bounds as condition and
+//! capturing groups are partitions titled with their number and name.
+//! Automata become state diagrams.
+
+use std::fmt::Write;
+
+use super::{parts, quantifier_label, Part, Transitions};
+use crate::{
+    ast::Token,
+    compiler::{Dfa, Nfa},
+};
+
+/// `text` with the characters of Creole markup escaped.
+fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|ch| match "~*_-/\"<>;[]".contains(ch) {
+            true => vec!['~', ch],
+            false => vec![ch],
+        })
+        .collect()
+}
+
+struct Activity {
+    out: String,
+    groups: usize,
+    depth: usize,
+}
+
+impl Activity {
+    fn line(&mut self, line: &str) {
+        let _ = writeln!(self.out, "{}{line}", "  ".repeat(self.depth));
+    }
+
+    fn action(&mut self, text: &str) {
+        self.line(&format!(":{};", escape(text)));
+    }
+
+    /// Writes what `body` does between an opening and a closing line,
+    /// indented.
+    fn nested(&mut self, open: &str, close: &str, body: impl FnOnce(&mut Self)) {
+        self.line(open);
+        self.depth += 1;
+        body(self);
+        self.depth -= 1;
+        self.line(close);
+    }
+
+    fn build(&mut self, tok: &Token) {
+        match tok {
+            Token::Conjunction(tokens) => {
+                for part in parts(tokens) {
+                    match part {
+                        Part::Text(text) => self.action(&text),
+                        Part::Token(tok) => self.build(tok),
+                    }
+                }
+            }
+            Token::Disjunction(tokens) if !tok.is_class() => match tokens.as_slice() {
+                [tok] => self.build(tok),
+                tokens => {
+                    for (i, tok) in tokens.iter().enumerate() {
+                        let branch = match i {
+                            0 => "if () then",
+                            _ if i == tokens.len() - 1 => "else",
+                            _ => "elseif () then",
+                        };
+
+                        self.line(branch);
+                        self.depth += 1;
+                        self.build(tok);
+                        self.depth -= 1;
+                    }
+                    self.line("endif");
+                }
+            },
+            Token::GreedyQuantifier(tok, min, max) => self.quantifier(tok, *min, *max, false),
+            Token::LazyQuantifier(tok, min, max) => self.quantifier(tok, *min, *max, true),
+            Token::Capturing(tok, name) => {
+                self.groups += 1;
+
+                let title = match name {
+                    Some(name) => format!("#{} {name}", self.groups),
+                    None => format!("#{}", self.groups),
+                };
+
+                self.nested(
+                    &format!("partition \"{}\" {{", escape(&title)),
+                    "}",
+                    |activity| activity.build(tok),
+                );
+            }
+            _ => self.action(&tok.to_string()),
+        }
+    }
+
+    fn quantifier(&mut self, tok: &Token, min: usize, max: Option<usize>, lazy: bool) {
+        let repeats = max.unwrap_or(2) > 1;
+        let lazy = match lazy {
+            true => ", lazy",
+            false => "",
+        };
+        let repeat = |activity: &mut Self| match repeats {
+            true => activity.nested(
+                "repeat",
+                &format!("repeat while ({}{lazy})", quantifier_label(min, max, "∞")),
+                |activity| activity.build(tok),
+            ),
+            false => activity.build(tok),
+        };
+
+        match min {
+            0 if repeats => self.nested("if () then", "endif", repeat),
+            0 => self.nested(&format!("if (optional{lazy}) then"), "endif", repeat),
+            _ => repeat(self),
+        }
+    }
+}
+
+/// The expression `tok` as an activity diagram.
+pub fn token(tok: &Token) -> String {
+    let mut activity = Activity {
+        out: String::from("@startuml\nstart\n"),
+        groups: 0,
+        depth: 0,
+    };
+
+    activity.build(tok);
+    activity.out.push_str("stop\n@enduml\n");
+    activity.out
+}
+
+fn automaton(transitions: &Transitions) -> String {
+    let mut out = String::from("@startuml\nhide empty description\nleft to right direction\n");
+
+    let _ = writeln!(out, "[*] --> s{}", transitions.start);
+
+    for (from, label, to) in &transitions.edges {
+        let _ = writeln!(out, "s{from} --> s{to} : {label}");
+    }
+
+    for state in &transitions.accepting {
+        let _ = writeln!(out, "s{state} --> [*]");
+    }
+
+    out.push_str("@enduml\n");
+    out
+}
+
+/// Every state of `nfa`.
+pub fn nfa(nfa: &Nfa) -> String {
+    automaton(&Transitions::from(nfa))
+}
+
+/// The live states of `dfa`, see [`Dfa::edges`].
+pub fn dfa(dfa: &Dfa) -> String {
+    automaton(&Transitions::from(dfa))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compiler, parser::parse_expr};
+
+    #[test]
+    fn test_token() {
+        let tok = parse_expr(r"a-b(?<x>c|\d|)+?e?".chars()).expect("parse");
+
+        assert_eq!(
+            token(&tok),
+            [
+                "@startuml",
+                "start",
+                ":a~-b;",
+                "repeat",
+                "  partition \"#1 x\" {",
+                "    if () then",
+                "      :c;",
+                "    elseif () then",
+                "      :\\d;",
+                "    else",
+                "    endif",
+                "  }",
+                "repeat while (1..∞, lazy)",
+                "if (optional) then",
+                "  :e;",
+                "endif",
+                "stop",
+                "@enduml",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_group_name() {
+        let tok = parse_expr(r#"(?<a"}<b>x)"#.chars()).expect("parse");
+
+        assert!(token(&tok).contains("partition \"#1 a~\"}~<b\" {\n  :x;\n}\n"));
+    }
+
+    #[test]
+    fn test_optional_loop() {
+        let tok = parse_expr("a{0,3}".chars()).expect("parse");
+
+        assert!(
+            token(&tok).contains("if () then\n  repeat\n    :a;\n  repeat while (..=3)\nendif\n")
+        );
+    }
+
+    #[test]
+    fn test_dfa() {
+        let dfa = dfa(&compiler::compile(
+            &parse_expr(r"\d+|x".chars()).expect("parse"),
+        ));
+
+        assert!(dfa.contains(
+            "[*] --> s0\ns0 --> s2 : \\d\ns0 --> s3 : x\ns2 --> s2 : \\d\ns2 --> [*]\ns3 --> [*]\n"
+        ));
+    }
+}
@@ -1,9 +1,12 @@
+use std::{fmt::Display, str::FromStr};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Color {
     #[default]
     Reset,
     Black,
     Red,
+    Green,
     Yellow,
     Blue,
     Magenta,
@@ -17,58 +20,102 @@ pub enum Color {
     LightMagenta,
     LightCyan,
     LightWhite,
+    /// A color of the 256-color palette of terminals.
+    Ansi256(u8),
+    /// A truecolor, by its red, green and blue components.
+    Rgb(u8, u8, u8),
 }
 
-pub struct ColorIterator {
-    idx: usize,
-    values: Vec<Color>,
-}
-
-impl ColorIterator {
-    fn new(values: Vec<Color>) -> Self {
-        if values.len() < 1 {
-            panic!("bad code");
+const NAMES: [(Color, &str); 17] = [
+    (Color::Reset, "reset"),
+    (Color::Black, "black"),
+    (Color::Red, "red"),
+    (Color::Green, "green"),
+    (Color::Yellow, "yellow"),
+    (Color::Blue, "blue"),
+    (Color::Magenta, "magenta"),
+    (Color::Cyan, "cyan"),
+    (Color::White, "white"),
+    (Color::LightBlack, "light-black"),
+    (Color::LightRed, "light-red"),
+    (Color::LightGreen, "light-green"),
+    (Color::LightYellow, "light-yellow"),
+    (Color::LightBlue, "light-blue"),
+    (Color::LightMagenta, "light-magenta"),
+    (Color::LightCyan, "light-cyan"),
+    (Color::LightWhite, "light-white"),
+];
+
+/// Written as parsed by [`Color::from_str`].
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Color::Ansi256(value) => write!(f, "{value}"),
+            Color::Rgb(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            color => {
+                let (_, name) = NAMES
+                    .iter()
+                    .find(|(named, _)| named == color)
+                    .expect("every named color");
+
+                f.write_str(name)
+            }
         }
-
-        Self { idx: 0, values }
     }
 }
 
-impl ColorIterator {
-    pub fn next_color(&mut self) -> Color {
-        self.next().unwrap()
-    }
-}
-
-impl Iterator for ColorIterator {
-    type Item = Color;
+/// A color name such as `green` or `light-blue`, a number of the 256-color
+/// palette such as `208`, or a truecolor such as `#ff8700`.
+impl FromStr for Color {
+    type Err = String;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.idx += 1;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((color, _)) = NAMES.iter().find(|(_, name)| *name == s) {
+            return Ok(*color);
+        }
+        if let Ok(value) = s.parse() {
+            return Ok(Color::Ansi256(value));
+        }
 
-        if self.idx == self.values.len() {
-            self.idx = 0;
+        let component = |i: usize| {
+            s.get(i..i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        };
+
+        match (
+            s.len(),
+            s.starts_with('#'),
+            component(1),
+            component(3),
+            component(5),
+        ) {
+            (7, true, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!(
+                "unknown color `{s}`, expected a name, 0 to 255 or #rrggbb"
+            )),
         }
+    }
+}
 
-        self.values.get(self.idx).copied()
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
-impl Color {
-    pub fn background_iter() -> ColorIterator {
-        ColorIterator::new(vec![
-            Color::Black,
-            Color::Red,
-            Color::Yellow,
-            Color::Blue,
-            Color::Magenta,
-            Color::Cyan,
-            Color::White,
-        ])
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Format {
     #[default]
     Reset,
@@ -80,6 +127,8 @@ pub enum Format {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct Style {
     pub background: Option<Color>,
     pub foreground: Option<Color>,
@@ -100,41 +149,29 @@ impl Style {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Styles {
-    width: usize,
-    height: usize,
-    s: Vec<Style>,
-}
-
-impl Styles {
-    pub fn set(&mut self, row: usize, col: usize, styles: &Styles) {
-        for r in 0..styles.height {
-            let offset = (row + r) * self.width + col;
-
-            for c in 0..styles.width {
-                self.s
-                    .get_mut(c + offset)
-                    .expect("style at offset")
-                    .apply(styles.get(r, c).unwrap());
-            }
-        }
-    }
-
-    pub fn get(&self, row: usize, col: usize) -> Option<&Style> {
-        let offset = row * self.width + col;
-        self.s.get(offset)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!("green".parse(), Ok(Color::Green));
+        assert_eq!("light-blue".parse(), Ok(Color::LightBlue));
+        assert_eq!("208".parse(), Ok(Color::Ansi256(208)));
+        assert_eq!("#ff8700".parse(), Ok(Color::Rgb(0xff, 0x87, 0)));
+        assert!("#ff87".parse::<Color>().is_err());
+        assert!("256".parse::<Color>().is_err());
+        assert!("teal".parse::<Color>().is_err());
     }
 
-    pub fn new(width: usize, height: usize) -> Self {
-        Self {
-            width,
-            height,
-            s: (0..width * height).map(|_| Style::default()).collect(),
+    #[test]
+    fn test_display_color() {
+        for color in [
+            Color::LightMagenta,
+            Color::Ansi256(42),
+            Color::Rgb(1, 2, 255),
+        ] {
+            assert_eq!(color.to_string().parse(), Ok(color));
         }
     }
-
-    pub fn clear(&mut self, s: Style) {
-        self.s.splice(.., (0..self.s.len()).map(|_| s));
-    }
 }
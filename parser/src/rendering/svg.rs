@@ -0,0 +1,407 @@
+//! SVG output of the diagrams.
+//!
+//! The layout is the one of the text diagrams: every cell of a [`Block`]
+//! becomes a rectangle of the drawing, box-drawing characters become track
+//! segments with rounded corners, and the remaining characters are drawn as
+//! text, boxed unless they belong to a class. Group and class regions are
+//! drawn behind the tracks.
+
+use std::fmt::Write;
+
+use super::block::{Block, Cell, Region, RegionKind};
+
+const CELL_WIDTH: usize = 10;
+const CELL_HEIGHT: usize = 20;
+/// Space around the diagram, in cells.
+const MARGIN: usize = 1;
+/// Space between nested regions covering the same cells.
+const INSET: usize = 3;
+
+const STYLE: &str = "\
+.track{fill:none;stroke:#333;stroke-width:2}\
+.lazy{stroke-dasharray:4 3}\
+.node{fill:#fff;stroke:#333;stroke-width:1.5}\
+.group{fill:#eef4ff;stroke:#6b8fd6;stroke-width:1}\
+.class{fill:#fff7e6;stroke:#d6a24a;stroke-width:1;stroke-dasharray:4 2}\
+text{font-family:monospace;font-size:14px;fill:#111}\
+text.label,text.name{font-size:11px;fill:#555}";
+
+/// The directions a track glyph leaves its cell in: left, up, right, down.
+fn directions(ch: char) -> Option<[bool; 4]> {
+    let directions = match ch {
+        '─' | '╌' => [true, false, true, false],
+        '│' => [false, true, false, true],
+        '┬' => [true, false, true, true],
+        '┴' => [true, true, true, false],
+        '┼' => [true, true, true, true],
+        '├' => [false, true, true, true],
+        '┤' => [true, true, false, true],
+        '╭' => [false, false, true, true],
+        '╮' => [true, false, false, true],
+        '╰' => [false, true, true, false],
+        '╯' => [true, true, false, false],
+        _ => return None,
+    };
+
+    Some(directions)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn contains(region: &Region, row: usize, col: usize) -> bool {
+    (region.row..region.row + region.height).contains(&row)
+        && (region.col..region.col + region.width).contains(&col)
+}
+
+/// Draws `block` as a standalone SVG document.
+pub fn render(block: &Block) -> String {
+    let width = (block.width() + 2 * MARGIN) * CELL_WIDTH;
+    let height = (block.height() + 2 * MARGIN) * CELL_HEIGHT;
+    let mut svg = String::new();
+
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    let _ = write!(svg, "<style>{STYLE}</style>");
+    let _ = write!(
+        svg,
+        r#"<g transform="translate({} {})">"#,
+        MARGIN * CELL_WIDTH,
+        MARGIN * CELL_HEIGHT
+    );
+
+    let rows = cells(block);
+
+    write_regions(&mut svg, block.regions());
+    write_tracks(&mut svg, block, &rows);
+    write_text(&mut svg, block, &rows);
+
+    svg.push_str("</g></svg>\n");
+    svg
+}
+
+/// The characters of the block, without the frames of the groups: their
+/// boxes are drawn instead, and only the track crossing their sides is kept.
+/// The right halves of wide characters are kept as such, to be part of the
+/// text around them.
+fn cells(block: &Block) -> Vec<Vec<char>> {
+    let mut rows: Vec<Vec<char>> = block
+        .rows()
+        .map(|row| row.iter().map(|cell| cell.ch).collect())
+        .collect();
+
+    for region in block
+        .regions()
+        .iter()
+        .filter(|region| matches!(region.kind, RegionKind::Group(_, _)))
+    {
+        let (bottom, right) = (
+            region.row + region.height - 1,
+            region.col + region.width - 1,
+        );
+
+        for (row, cells) in rows
+            .iter_mut()
+            .enumerate()
+            .take(bottom + 1)
+            .skip(region.row)
+        {
+            for (col, ch) in cells
+                .iter_mut()
+                .enumerate()
+                .take(right + 1)
+                .skip(region.col)
+            {
+                if row == region.row || row == bottom {
+                    *ch = ' ';
+                } else if col == region.col || col == right {
+                    *ch = if *ch == '┼' { '─' } else { ' ' };
+                }
+            }
+        }
+    }
+
+    rows
+}
+
+/// Group and class boxes, outermost first so inner ones are drawn on top.
+fn write_regions(svg: &mut String, regions: &[Region]) {
+    for (i, region) in regions.iter().enumerate().rev() {
+        let class = match region.kind {
+            RegionKind::Group(_, _) => "group",
+            RegionKind::Class => "class",
+            RegionKind::Label | RegionKind::Token(_) => continue,
+        };
+        let inset = INSET
+            * regions[i + 1..]
+                .iter()
+                .filter(|outer| matches!(outer.kind, RegionKind::Group(_, _) | RegionKind::Class))
+                .filter(|outer| {
+                    contains(outer, region.row, region.col)
+                        && contains(
+                            outer,
+                            region.row + region.height - 1,
+                            region.col + region.width - 1,
+                        )
+                })
+                .count();
+        let x = region.col * CELL_WIDTH + inset;
+        let y = region.row * CELL_HEIGHT + inset;
+        let width = (region.width * CELL_WIDTH).saturating_sub(2 * inset);
+        let height = (region.height * CELL_HEIGHT).saturating_sub(2 * inset);
+
+        let _ = write!(
+            svg,
+            r#"<rect class="{class}" x="{x}" y="{y}" width="{width}" height="{height}" rx="6"/>"#
+        );
+
+        if let RegionKind::Group(number, name) = &region.kind {
+            let title = match name {
+                Some(name) => format!("#{number} {name}"),
+                None => format!("#{number}"),
+            };
+
+            let _ = write!(
+                svg,
+                r#"<text class="name" x="{}" y="{}">{}</text>"#,
+                x + 2,
+                y,
+                escape(&title)
+            );
+        }
+    }
+}
+
+/// One path for all the track glyphs, and one for the dashed ones of lazy
+/// quantifiers. Straight halves of adjacent cells are merged into single
+/// segments and corners are rounded.
+fn write_tracks(svg: &mut String, block: &Block, rows: &[Vec<char>]) {
+    let (half_width, half_height) = (CELL_WIDTH / 2, CELL_HEIGHT / 2);
+    let mut path = String::new();
+    let mut dashed = String::new();
+    let at = |row: usize, col: usize| directions(rows[row][col]);
+    let is_corner = |ch: char| "╭╮╰╯".contains(ch);
+
+    // Horizontal segments, row by row.
+    for (row, cells) in rows.iter().enumerate() {
+        let y = row * CELL_HEIGHT + half_height;
+        let mut start = None;
+
+        for col in 0..=cells.len() {
+            let [left, _, right, _] = match col < cells.len() && !is_corner(cells[col]) {
+                true => at(row, col).unwrap_or_default(),
+                false => [false; 4],
+            };
+            let x = col * CELL_WIDTH;
+
+            match (left, right) {
+                (false, _) => {
+                    if let Some((from, lazy)) = start.take() {
+                        let path = if lazy { &mut dashed } else { &mut path };
+                        let _ = write!(path, "M{from} {y}H{x}");
+                    }
+                    if right {
+                        start = Some((x + half_width, false));
+                    }
+                }
+                (true, false) => {
+                    let (from, _) = start.take().unwrap_or((x, false));
+                    let _ = write!(path, "M{from} {y}H{}", x + half_width);
+                }
+                (true, true) => start = start.or(Some((x, cells[col] == '╌'))),
+            }
+        }
+    }
+
+    // Vertical segments, column by column.
+    for col in 0..block.width() {
+        let x = col * CELL_WIDTH + half_width;
+        let mut start = None;
+
+        for row in 0..=rows.len() {
+            let [_, up, _, down] = match row < rows.len() && !is_corner(rows[row][col]) {
+                true => at(row, col).unwrap_or_default(),
+                false => [false; 4],
+            };
+            let y = row * CELL_HEIGHT;
+
+            match (up, down) {
+                (false, _) => {
+                    if let Some(from) = start.take() {
+                        let _ = write!(path, "M{x} {from}V{y}");
+                    }
+                    if down {
+                        start = Some(y + half_height);
+                    }
+                }
+                (true, false) => {
+                    let from = start.take().unwrap_or(y);
+                    let _ = write!(path, "M{x} {from}V{}", y + half_height);
+                }
+                (true, true) => start = start.or(Some(y)),
+            }
+        }
+    }
+
+    // Corners, as quarter curves between the middles of two cell edges.
+    for (row, cells) in rows.iter().enumerate() {
+        for (col, ch) in cells.iter().enumerate() {
+            let (x, y) = (col * CELL_WIDTH, row * CELL_HEIGHT);
+            let (cx, cy) = (x + half_width, y + half_height);
+            let (horizontal, vertical) = match ch {
+                '╭' => (x + CELL_WIDTH, y + CELL_HEIGHT),
+                '╮' => (x, y + CELL_HEIGHT),
+                '╰' => (x + CELL_WIDTH, y),
+                '╯' => (x, y),
+                _ => continue,
+            };
+
+            let _ = write!(path, "M{horizontal} {cy}Q{cx} {cy} {cx} {vertical}");
+        }
+    }
+
+    if !path.is_empty() {
+        let _ = write!(svg, r#"<path class="track" d="{path}"/>"#);
+    }
+    if !dashed.is_empty() {
+        let _ = write!(svg, r#"<path class="track lazy" d="{dashed}"/>"#);
+    }
+}
+
+/// Runs of characters on a row, in boxes unless they label a loop. Every
+/// character is centered on its cells, and combining marks on the character
+/// they are drawn over.
+fn write_text(svg: &mut String, block: &Block, rows: &[Vec<char>]) {
+    for ((row, cells), block_cells) in rows.iter().enumerate().zip(block.rows()) {
+        let mut col = 0;
+
+        while col < cells.len() {
+            let is_text = |col: usize| cells[col] != ' ' && directions(cells[col]).is_none();
+
+            if !is_text(col) {
+                col += 1;
+                continue;
+            }
+
+            let start = col;
+
+            while col < cells.len() && is_text(col) {
+                col += 1;
+            }
+
+            let text: String = block_cells[start..col].iter().map(Cell::text).collect();
+            let within = |kind: RegionKind| {
+                block
+                    .regions()
+                    .iter()
+                    .any(|region| region.kind == kind && contains(region, row, start))
+            };
+            let label = within(RegionKind::Label);
+            let xs: Vec<String> = (start..col)
+                .filter(|col| !block_cells[*col].is_continuation())
+                .flat_map(|col| {
+                    let width = match block_cells.get(col + 1) {
+                        Some(next) if next.is_continuation() => 2,
+                        _ => 1,
+                    };
+                    let x = (col * CELL_WIDTH + width * CELL_WIDTH / 2).to_string();
+
+                    vec![x; 1 + block_cells[col].marks.len()]
+                })
+                .collect();
+            let y = row * CELL_HEIGHT + CELL_HEIGHT * 3 / 4;
+
+            if label {
+                let _ = write!(
+                    svg,
+                    r#"<text class="label" x="{}" y="{y}" text-anchor="middle">{}</text>"#,
+                    xs.join(" "),
+                    escape(&text)
+                );
+                continue;
+            }
+
+            // Class items are framed by the class itself.
+            if !within(RegionKind::Class) {
+                let _ = write!(
+                    svg,
+                    r#"<rect class="node" x="{}" y="{}" width="{}" height="{}" rx="4"/>"#,
+                    start * CELL_WIDTH + 1,
+                    row * CELL_HEIGHT + 2,
+                    (col - start) * CELL_WIDTH - 2,
+                    CELL_HEIGHT - 4
+                );
+            }
+            let _ = write!(
+                svg,
+                r#"<text x="{}" y="{y}" text-anchor="middle">{}</text>"#,
+                xs.join(" "),
+                escape(&text)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse_expr, rendering::render_token};
+
+    fn svg(pattern: &str) -> String {
+        render(&render_token(&parse_expr(pattern.chars()).expect("parse")))
+    }
+
+    #[test]
+    fn test_document() {
+        let svg = svg("a<b");
+
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="50" height="60""#)
+        );
+        assert!(svg.ends_with("</g></svg>\n"));
+        assert!(svg.contains(r#"<text x="5 15 25" y="15" text-anchor="middle">a&lt;b</text>"#));
+        assert!(!svg.contains("<path"));
+    }
+
+    #[test]
+    fn test_wide_text() {
+        // Wide characters are centered on both their cells, marks on the
+        // character under them.
+        assert!(svg("日e\u{301}")
+            .contains("<text x=\"10 25 25\" y=\"15\" text-anchor=\"middle\">日e\u{301}</text>"));
+    }
+
+    #[test]
+    fn test_tracks() {
+        let svg = svg("a|b");
+
+        // The rounded corners of the top branch.
+        assert!(svg.contains("M10 10Q5 10 5 20"));
+        assert!(svg.contains("M20 10Q25 10 25 20"));
+        // The straight track of the bottom branch, through both junctions.
+        assert!(svg.contains("M0 30H10"));
+    }
+
+    #[test]
+    fn test_lazy_tracks() {
+        assert!(svg("a+?").contains(r#"<path class="track lazy" d="M20 70H50"/>"#));
+        assert!(!svg("a+").contains(r#"class="track lazy""#));
+    }
+
+    #[test]
+    fn test_regions() {
+        let svg = svg("(?<year>[0-9a-f])+");
+
+        assert!(svg.contains(r#"<rect class="group""#));
+        assert!(svg.contains(r#"<rect class="class""#));
+        assert!(svg.contains(r#"<text class="name" x="12" y="40">#1 year</text>"#));
+        assert!(svg.contains(r#"<text class="label""#));
+        assert!(!svg.contains(r#"<rect class="node""#));
+    }
+}
@@ -0,0 +1,147 @@
+use super::style::{Color, Format, Style};
+
+/// The styles of the parts of a diagram, by what they stand for, and of the
+/// errors reported about an expression.
+///
+/// With the `serde` feature, themes can be read from files with a table per
+/// role, such as `[literal]` with `foreground = "#ff8700"` and
+/// `format = "bold"`. The roles left out keep their style from the default
+/// theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Theme {
+    /// Literal characters.
+    pub literal: Style,
+    /// Escaped characters that would be invisible, such as `\t`.
+    pub escape: Style,
+    /// Character classes: shorthands like `\d`, `.`, ranges and the rails of
+    /// bracketed classes.
+    pub class: Style,
+    /// `^`, `$` and `\b`.
+    pub anchor: Style,
+    /// The frames of capturing groups, with their number and name.
+    pub group: Style,
+    /// The loops and bypasses of quantifiers, with their bounds.
+    pub quantifier: Style,
+    /// The rails of alternations and the tracks joining wrapped lines.
+    pub rail: Style,
+    /// Where an expression is wrong.
+    pub error: Style,
+    /// Drawn over the tokens and tracks a match went through, and over what
+    /// is written about it.
+    pub path: Style,
+}
+
+/// The names of the built-in themes, see [`Theme::builtin`].
+pub const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+const fn style(foreground: Option<Color>, format: Option<Format>) -> Style {
+    Style {
+        background: None,
+        foreground,
+        format,
+    }
+}
+
+const fn background(color: Color) -> Style {
+    Style {
+        background: Some(color),
+        foreground: None,
+        format: None,
+    }
+}
+
+impl Theme {
+    /// For terminals with a dark background.
+    pub const fn dark() -> Self {
+        Self {
+            literal: style(None, Some(Format::Bold)),
+            escape: style(Some(Color::Magenta), None),
+            class: style(Some(Color::Blue), Some(Format::Bold)),
+            anchor: style(Some(Color::Red), Some(Format::Bold)),
+            group: style(Some(Color::Green), None),
+            quantifier: style(Some(Color::Yellow), None),
+            rail: style(Some(Color::LightBlack), None),
+            error: style(Some(Color::LightRed), Some(Format::Bold)),
+            path: background(Color::Ansi256(22)),
+        }
+    }
+
+    /// For terminals with a light background, avoiding the yellows and
+    /// light colors that fade into it.
+    pub const fn light() -> Self {
+        Self {
+            literal: style(None, Some(Format::Bold)),
+            escape: style(Some(Color::Magenta), None),
+            class: style(Some(Color::Blue), Some(Format::Bold)),
+            anchor: style(Some(Color::Red), Some(Format::Bold)),
+            group: style(Some(Color::Ansi256(28)), None),
+            quantifier: style(Some(Color::Ansi256(130)), None),
+            rail: style(Some(Color::Ansi256(244)), None),
+            error: style(Some(Color::Red), Some(Format::Bold)),
+            path: background(Color::Ansi256(194)),
+        }
+    }
+
+    /// Bright colors only, everything but the tracks in bold.
+    pub const fn high_contrast() -> Self {
+        Self {
+            literal: style(Some(Color::LightWhite), Some(Format::Bold)),
+            escape: style(Some(Color::LightMagenta), Some(Format::Bold)),
+            class: style(Some(Color::LightCyan), Some(Format::Bold)),
+            anchor: style(Some(Color::LightYellow), Some(Format::Bold)),
+            group: style(Some(Color::LightGreen), Some(Format::Bold)),
+            quantifier: style(Some(Color::LightYellow), None),
+            rail: style(Some(Color::White), None),
+            error: style(Some(Color::LightWhite), Some(Format::Reverse)),
+            path: style(None, Some(Format::Reverse)),
+        }
+    }
+
+    /// The built-in theme called `name`, one of [`BUILTIN_THEMES`].
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+}
+
+/// The dark theme.
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        for name in BUILTIN_THEMES {
+            assert!(Theme::builtin(name).is_some());
+        }
+        assert_eq!(Theme::builtin("solarized"), None);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let theme: Theme = serde_json::from_str(
+            r##"{"literal": {"foreground": "#ff8700", "format": "italic"}, "rail": {"background": "236"}}"##,
+        )
+        .expect("theme");
+
+        assert_eq!(
+            theme.literal,
+            style(Some(Color::Rgb(0xff, 0x87, 0)), Some(Format::Italic))
+        );
+        assert_eq!(theme.rail.background, Some(Color::Ansi256(236)));
+        assert_eq!(theme.group, Theme::dark().group);
+        assert!(serde_json::from_str::<Theme>(r#"{"rails": {}}"#).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! The serialized form of parsed expressions, for tools that read trex's
+//! output instead of parsing patterns themselves.
+//!
+//! Only available with the `serde` feature. A [`Document`] is an object with
+//! three fields:
+//!
+//! - `version`: the [`SCHEMA_VERSION`] the document was written with.
+//!   Reading a document of another version fails.
+//! - `ast`: the root [`Token`].
+//! - `spans`: the source range of every token as `{"start": 0, "end": 2}`,
+//!   in character offsets, ordered as [`Token::walk`] visits the tokens.
+//!   Empty when the expression was not parsed from a pattern.
+//!
+//! Tokens are objects with a `type` field naming the [`Token`] variant and,
+//! unless the variant has no fields, an `args` field holding them: a single
+//! value for one field, an array for more. Characters are one-character
+//! strings and unbounded quantifiers have a `null` maximum. For example the
+//! tree of `a{2,}` is:
+//!
+//! ```json
+//! {
+//!   "type": "Conjunction",
+//!   "args": [
+//!     {
+//!       "type": "GreedyQuantifier",
+//!       "args": [{ "type": "Literal", "args": "a" }, 2, null]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Adding token types does not change the version, so readers should expect
+//! `type` values they do not know. Any other change to the layout does.
+//! [`Document`] itself has no way to hold such a token: deserializing one
+//! fails with an unknown variant error, so a document written by a newer
+//! trex can only be read back by a trex that knows its tokens.
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::ast::{Span, Token};
+
+/// The version of the layout described in this module.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A parsed expression with its source spans, see the [module
+/// documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(deserialize_with = "version")]
+    pub version: u32,
+    pub ast: Token,
+    pub spans: Vec<Span>,
+}
+
+impl Document {
+    pub fn new(ast: Token, spans: Vec<Span>) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            ast,
+            spans,
+        }
+    }
+}
+
+fn version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    match u32::deserialize(deserializer)? {
+        SCHEMA_VERSION => Ok(SCHEMA_VERSION),
+        version => Err(de::Error::custom(format!(
+            "unsupported schema version {version}, expected {SCHEMA_VERSION}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr_with_spans;
+
+    #[test]
+    fn test_layout() {
+        let (ast, spans) = parse_expr_with_spans("(?<x>a)+$".chars()).expect("parse");
+        let json = serde_json::to_string(&Document::new(ast, spans)).expect("serialize");
+
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"version":1,"ast":{"type":"Conjunction","args":["#,
+                r#"{"type":"GreedyQuantifier","args":[{"type":"Capturing","args":[{"type":"Conjunction","args":[{"type":"Literal","args":"a"}]},"x"]},1,null]},"#,
+                r#"{"type":"End"}]},"spans":["#,
+                r#"{"start":0,"end":9},{"start":0,"end":8},{"start":0,"end":7},{"start":5,"end":6},{"start":5,"end":6},{"start":8,"end":9}]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (ast, spans) = parse_expr_with_spans(r"^[\d_a-f]{2,}?|.\b".chars()).expect("parse");
+        let document = Document::new(ast, spans);
+        let json = serde_json::to_string(&document).expect("serialize");
+
+        assert_eq!(
+            serde_json::from_str::<Document>(&json).expect("deserialize"),
+            document
+        );
+    }
+
+    #[test]
+    fn test_version() {
+        let json = r#"{"version":2,"ast":{"type":"Any"},"spans":[]}"#;
+        let error = serde_json::from_str::<Document>(json).expect_err("version 2");
+
+        assert!(error.to_string().contains("unsupported schema version 2"));
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let json = r#"{"version":1,"ast":{"type":"Recursion"},"spans":[]}"#;
+        let error = serde_json::from_str::<Document>(json).expect_err("unknown type");
+
+        assert!(error.to_string().contains("unknown variant `Recursion`"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Normalization pass removing the redundant structure left by the parser.
+//!
+//! The rewrites never change which strings an expression matches, nor the
+//! order in which alternatives are tried. Capturing groups are kept with
+//! their numbers, so nothing is removed or factored out of the way of one.
+
+use crate::ast::{Token, VisitorMut};
+
+/// Simplifies `tok`:
+///
+/// * nested conjunctions and disjunctions are flattened, and single-child
+///   ones replaced by their child;
+/// * overlapping ranges and literals of adjacent single-character
+///   alternatives are merged, so `a|b|[a-c]` becomes `[a-c]`;
+/// * prefixes shared by adjacent alternatives are factored out, so `abc|abd`
+///   becomes `ab[cd]`;
+/// * `x{1}` becomes `x` and `x{0}` disappears.
+///
+/// Prefixes holding a capturing group are not factored and `x{0}` stays
+/// when `x` holds one, since either would remove a group and renumber the
+/// ones after it. Neither are prefixes that can match in more than one way,
+/// holding a quantifier or an alternation: `a?aa|a?` tries `a?` again for the
+/// second alternative, which `a?(?:aa)?` would not.
+pub fn simplify(tok: Token) -> Token {
+    let mut tok = tok;
+
+    tok.walk_mut(&mut Simplifier);
+    tok
+}
+
+struct Simplifier;
+
+impl VisitorMut for Simplifier {
+    fn visit_post(&mut self, tok: &mut Token) {
+        let simplified = match std::mem::replace(tok, Token::Conjunction(vec![])) {
+            Token::Conjunction(tokens) => simplify_conjunction(tokens),
+            Token::Disjunction(tokens) => simplify_disjunction(tokens),
+            Token::GreedyQuantifier(tok, min, max) => {
+                simplify_quantifier(*tok, min, max, Token::GreedyQuantifier)
+            }
+            Token::LazyQuantifier(tok, min, max) => {
+                simplify_quantifier(*tok, min, max, Token::LazyQuantifier)
+            }
+            other => other,
+        };
+
+        *tok = simplified;
+    }
+}
+
+fn simplify_conjunction(tokens: Vec<Token>) -> Token {
+    let mut flat = vec![];
+
+    for tok in tokens {
+        match tok {
+            Token::Conjunction(children) => flat.extend(children),
+            tok => flat.push(tok),
+        }
+    }
+
+    if flat.len() == 1 {
+        flat.pop().unwrap()
+    } else {
+        Token::Conjunction(flat)
+    }
+}
+
+fn simplify_quantifier(
+    tok: Token,
+    min: usize,
+    max: Option<usize>,
+    quantifier: fn(Box<Token>, usize, Option<usize>) -> Token,
+) -> Token {
+    match (min, max) {
+        (1, Some(1)) => tok,
+        (0, Some(0)) if !has_capture(&tok) => Token::Conjunction(vec![]),
+        _ if tok == Token::Conjunction(vec![]) => tok,
+        _ => quantifier(Box::new(tok), min, max),
+    }
+}
+
+fn simplify_disjunction(tokens: Vec<Token>) -> Token {
+    let mut flat = vec![];
+
+    for tok in tokens {
+        match tok {
+            Token::Disjunction(children) => flat.extend(children),
+            tok => flat.push(tok),
+        }
+    }
+
+    let mut alternatives = merge_characters(factor_prefixes(flat));
+
+    match alternatives.as_slice() {
+        [_] => alternatives.pop().unwrap(),
+        [rest @ .., Token::Conjunction(last)] if last.is_empty() && !rest.is_empty() => {
+            let rest = simplify_disjunction(rest.to_vec());
+            Token::GreedyQuantifier(Box::new(rest), 0, Some(1))
+        }
+        [Token::Conjunction(first), other] if first.is_empty() => {
+            Token::LazyQuantifier(Box::new(other.clone()), 0, Some(1))
+        }
+        _ => Token::Disjunction(alternatives),
+    }
+}
+
+/// Whether `tok` is or holds a capturing group.
+fn has_capture(tok: &Token) -> bool {
+    matches!(tok, Token::Capturing(..)) || tok.children().iter().any(has_capture)
+}
+
+/// Whether `tok` can match a given string in one way only, with no
+/// quantifier or alternation to backtrack into.
+fn is_fixed(tok: &Token) -> bool {
+    match tok {
+        Token::GreedyQuantifier(..) | Token::LazyQuantifier(..) => false,
+        Token::Disjunction(_) if !tok.is_class() => false,
+        tok => tok.children().iter().all(is_fixed),
+    }
+}
+
+fn sequence(tok: &Token) -> &[Token] {
+    match tok {
+        Token::Conjunction(tokens) => tokens,
+        tok => std::slice::from_ref(tok),
+    }
+}
+
+/// Factors the prefix shared by runs of adjacent alternatives starting with
+/// the same token. Only adjacent alternatives are grouped so the order in
+/// which they are tried stays the same, and only tokens matching in one way
+/// are factored so the backtracking into them does too.
+fn factor_prefixes(alternatives: Vec<Token>) -> Vec<Token> {
+    let mut factored = vec![];
+    let mut i = 0;
+
+    while i < alternatives.len() {
+        let first = sequence(&alternatives[i]).first();
+        let run = alternatives[i..]
+            .iter()
+            .take_while(|alt| first.is_some() && sequence(alt).first() == first)
+            .count()
+            .max(1);
+
+        if run == 1 {
+            factored.push(alternatives[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let group = &alternatives[i..i + run];
+        let prefix_len = (1..)
+            .take_while(|len| {
+                let prefix = sequence(&group[0]).get(..*len);
+                prefix.is_some_and(|prefix| {
+                    !has_capture(&prefix[*len - 1]) && is_fixed(&prefix[*len - 1])
+                }) && group.iter().all(|alt| sequence(alt).get(..*len) == prefix)
+            })
+            .last()
+            .unwrap_or(0);
+
+        if prefix_len == 0 {
+            factored.extend_from_slice(group);
+            i += run;
+            continue;
+        }
+
+        let mut tokens = sequence(&group[0])[..prefix_len].to_vec();
+        let suffixes = group
+            .iter()
+            .map(|alt| simplify_conjunction(sequence(alt)[prefix_len..].to_vec()))
+            .collect();
+
+        tokens.push(simplify_disjunction(suffixes));
+        factored.push(simplify_conjunction(tokens));
+        i += run;
+    }
+
+    factored
+}
+
+fn char_range(tok: &Token) -> Option<(char, char)> {
+    match tok {
+        Token::Literal(ch) => Some((*ch, *ch)),
+        Token::AsciiRange(start, end) => Some((*start.min(end), *start.max(end))),
+        _ => None,
+    }
+}
+
+/// Merges the literals and ranges of runs of adjacent class items into
+/// sorted, non-overlapping ranges. When other alternatives remain, each run
+/// becomes its own class.
+fn merge_characters(alternatives: Vec<Token>) -> Vec<Token> {
+    if alternatives.iter().all(Token::is_class_item) {
+        return merge_run(alternatives);
+    }
+
+    let mut merged = vec![];
+    let mut run: Vec<Token> = vec![];
+
+    for tok in alternatives.into_iter().map(Some).chain([None]) {
+        match tok {
+            Some(tok) if tok.is_class_item() => run.push(tok),
+            tok => {
+                let mut class = merge_run(std::mem::take(&mut run));
+
+                match class.len() {
+                    0 => {}
+                    1 => merged.push(class.pop().unwrap()),
+                    _ => merged.push(Token::Disjunction(class)),
+                }
+
+                merged.extend(tok);
+            }
+        }
+    }
+
+    merged
+}
+
+fn merge_run(run: Vec<Token>) -> Vec<Token> {
+    let mut ranges: Vec<(char, char)> = run.iter().filter_map(char_range).collect();
+    let mut others: Vec<Token> = vec![];
+
+    for tok in run.into_iter().filter(|tok| char_range(tok).is_none()) {
+        if !others.contains(&tok) {
+            others.push(tok);
+        }
+    }
+
+    ranges.sort();
+
+    let mut merged: Vec<(char, char)> = vec![];
+
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if (*last_end as u32) + 1 >= start as u32 => {
+                *last_end = (*last_end).max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .flat_map(|(start, end)| match end as u32 - start as u32 {
+            0 => vec![Token::Literal(start)],
+            1 => vec![Token::Literal(start), Token::Literal(end)],
+            _ => vec![Token::AsciiRange(start, end)],
+        })
+        .chain(others)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    fn simplified(pattern: &str) -> String {
+        simplify(parse_expr(pattern.chars()).expect("parse")).to_string()
+    }
+
+    #[test]
+    fn test_flattens_nesting() {
+        assert_eq!(simplified("a(?:b(?:c))d"), "abcd");
+        assert_eq!(simplified("a|(?:b|cd)"), "[ab]|cd");
+        assert_eq!(simplified("(?:a)"), "a");
+    }
+
+    #[test]
+    fn test_merges_class_ranges() {
+        assert_eq!(simplified("[a-dc-fx]"), "[a-fx]");
+        assert_eq!(simplified("[ba\\dcb\\d]"), "[a-c\\d]");
+        assert_eq!(simplified("x|y|[a-z]"), "[a-z]");
+    }
+
+    #[test]
+    fn test_removes_unit_quantifiers() {
+        assert_eq!(simplified("ab{1}c{0}"), "ab");
+        assert_eq!(simplified("(?:ab){1,1}?"), "ab");
+    }
+
+    #[test]
+    fn test_factors_prefixes() {
+        assert_eq!(simplified("abc|abd"), "ab[cd]");
+        assert_eq!(simplified("abc|abd|x|abe"), "ab[cd]|x|abe");
+        assert_eq!(simplified("ab|abc"), "abc{0,1}?");
+        assert_eq!(simplified("abc|ab"), "abc?");
+        assert_eq!(simplified("(?<n>ab|ac)"), "(?<n>a[bc])");
+        assert_eq!(simplified("[ab]c|[ab]d"), "[ab][cd]");
+    }
+
+    #[test]
+    fn test_keeps_backtracking_prefixes() {
+        assert_eq!(simplified("a?aa|a?"), "a?aa|a?");
+        assert_eq!(simplified("ab*c|ab*d"), "a(?:b*c|b*d)");
+    }
+
+    #[test]
+    fn test_keeps_capturing_groups() {
+        assert_eq!(simplified("(a)b|(a)c"), "(a)b|(a)c");
+        assert_eq!(simplified("a(b)c|a(b)d"), "a(?:(b)c|(b)d)");
+        assert_eq!(simplified("x(a){0}(b)"), "x(a){0}(b)");
+    }
+
+    #[test]
+    fn test_keeps_alternative_order() {
+        assert_eq!(simplified("ab|cd|ae"), "ab|cd|ae");
+        assert_eq!(simplified("b|xy|a"), "b|xy|a");
+    }
+}
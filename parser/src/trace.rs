@@ -0,0 +1,439 @@
+//! Matching of an input against an expression, keeping the path the match
+//! took through the syntax tree: the alternatives chosen, how many times each
+//! loop ran and what the groups captured.
+//!
+//! The input is searched like a backtracking engine such as PCRE does:
+//! alternatives are tried in order, greedy quantifiers try one more time
+//! before stopping and lazy ones the other way around, and the match
+//! starting the leftmost wins. The choices left to go back to are kept on a
+//! stack of their own rather than on the call stack, so that long inputs do
+//! not overflow it.
+
+use std::rc::Rc;
+
+use crate::{
+    ast::Token,
+    compiler::ranges::{contains, is_word, token_ranges},
+    generate::size,
+};
+
+/// The most tokens [`trace`] tries to match before giving up, so that
+/// catastrophic backtracking does not hang it.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// Something that happened on the path of a match, at a token numbered in
+/// pre-order like in [`Token::walk`]. Positions count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// A character, a class or an assertion matched.
+    Matched(usize),
+    /// An alternative of an alternation was taken, by index.
+    Alternative(usize, usize),
+    /// A quantifier stopped after running its token this many times.
+    Repeated(usize, usize),
+    /// A capturing group captured the characters from the first position to
+    /// the second.
+    Captured(usize, usize, usize),
+}
+
+/// The path of a match, see [`trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    chars: Vec<char>,
+    /// The position of the first matched character.
+    pub start: usize,
+    /// The position after the last matched character.
+    pub end: usize,
+    /// Everything that happened on the path, in order.
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    /// The characters of the input from `start` to `end`.
+    pub fn text(&self, start: usize, end: usize) -> String {
+        self.chars[start..end].iter().collect()
+    }
+
+    /// The matched part of the input.
+    pub fn matched(&self) -> String {
+        self.text(self.start, self.end)
+    }
+
+    /// Whether the token `id` matched a character or an assertion.
+    pub fn is_matched(&self, id: usize) -> bool {
+        self.steps.contains(&Step::Matched(id))
+    }
+
+    /// The alternatives taken at the alternation `id`, once per time it was
+    /// reached.
+    pub fn alternatives(&self, id: usize) -> Vec<usize> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Alternative(at, i) if *at == id => Some(*i),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// How many times the quantifier `id` ran its token, once per time it
+    /// was reached.
+    pub fn repeats(&self, id: usize) -> Vec<usize> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Repeated(at, count) if *at == id => Some(*count),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// What the group `id` captured last, as a start and end position.
+    pub fn capture(&self, id: usize) -> Option<(usize, usize)> {
+        self.steps.iter().rev().find_map(|step| match step {
+            Step::Captured(at, start, end) if *at == id => Some((*start, *end)),
+            _ => None,
+        })
+    }
+}
+
+/// Why [`trace`] gave up before finding a match or ruling one out.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("gave up after {} steps", STEP_LIMIT)]
+pub struct StepLimit;
+
+/// A quantifier being matched.
+#[derive(Clone, Copy)]
+struct Loop<'t> {
+    tok: &'t Token,
+    id: usize,
+    min: usize,
+    max: Option<usize>,
+    lazy: bool,
+}
+
+/// What is left to match after a token, innermost first, shared by the
+/// choices still to try.
+type Continuation<'t> = Option<Rc<Frame<'t>>>;
+
+struct Frame<'t> {
+    kind: FrameKind<'t>,
+    next: Continuation<'t>,
+}
+
+enum FrameKind<'t> {
+    /// The rest of a sequence, the first token numbered by the `usize`.
+    Sequence(&'t [Token], usize),
+    /// The end of a run of a quantifier, its token having run the given
+    /// number of times since the loop started, from a position.
+    Repeat(Loop<'t>, usize, usize),
+    /// The end of a capturing group starting at a position.
+    Capture(usize, usize),
+}
+
+/// Something to match from a position.
+enum Goal<'t> {
+    /// A token, by pre-order number, and then the rest.
+    Token(&'t Token, usize, usize, Continuation<'t>),
+    /// Only the rest.
+    Rest(usize, Continuation<'t>),
+}
+
+/// A goal to go back to when the current one fails: the steps are cut back
+/// to the mark and then `step` is taken, if any.
+struct Choice<'t> {
+    goal: Goal<'t>,
+    mark: usize,
+    step: Option<Step>,
+}
+
+fn push<'t>(kind: FrameKind<'t>, next: Continuation<'t>) -> Continuation<'t> {
+    Some(Rc::new(Frame { kind, next }))
+}
+
+struct Matcher<'a, 't> {
+    chars: &'a [char],
+    steps: Vec<Step>,
+    choices: Vec<Choice<'t>>,
+    budget: usize,
+}
+
+impl<'t> Matcher<'_, 't> {
+    /// Matches `goal` and then the rest of what it holds, going back to the
+    /// choices left when it fails, and returns where the match ended.
+    fn run(&mut self, mut goal: Goal<'t>) -> Result<Option<usize>, StepLimit> {
+        loop {
+            let next = match goal {
+                Goal::Token(tok, id, pos, k) => self.token(tok, id, pos, k)?,
+                Goal::Rest(pos, None) => return Ok(Some(pos)),
+                Goal::Rest(pos, Some(frame)) => self.rest(pos, &frame),
+            };
+
+            goal = match next {
+                Some(next) => next,
+                None => match self.choices.pop() {
+                    Some(choice) => {
+                        self.steps.truncate(choice.mark);
+                        self.steps.extend(choice.step);
+                        choice.goal
+                    }
+                    None => return Ok(None),
+                },
+            };
+        }
+    }
+
+    /// The goal after matching `tok`, numbered `id`, at `pos`, or `None`
+    /// when the next choice is to be tried: when `tok` does not match there
+    /// or when it pushed the ways it can match as choices.
+    fn token(
+        &mut self,
+        tok: &'t Token,
+        id: usize,
+        pos: usize,
+        k: Continuation<'t>,
+    ) -> Result<Option<Goal<'t>>, StepLimit> {
+        if self.budget == 0 {
+            return Err(StepLimit);
+        }
+        self.budget -= 1;
+
+        let matched = |m: &mut Self, end| {
+            m.steps.push(Step::Matched(id));
+            Goal::Rest(end, k.clone())
+        };
+
+        Ok(match tok {
+            Token::Start => (pos == 0).then(|| matched(self, pos)),
+            Token::End => (pos == self.chars.len()).then(|| matched(self, pos)),
+            Token::WordBoundary => {
+                let before = pos > 0 && is_word(self.chars[pos - 1]);
+                let after = pos < self.chars.len() && is_word(self.chars[pos]);
+
+                (before != after).then(|| matched(self, pos))
+            }
+            Token::Conjunction(children) => Some(Goal::Rest(
+                pos,
+                push(FrameKind::Sequence(children, id + 1), k),
+            )),
+            Token::Disjunction(children) if !tok.is_class() => {
+                let mark = self.steps.len();
+                let mut child_id = id + 1;
+                let mut alternatives = vec![];
+
+                for (i, child) in children.iter().enumerate() {
+                    alternatives.push(Choice {
+                        goal: Goal::Token(child, child_id, pos, k.clone()),
+                        mark,
+                        step: Some(Step::Alternative(id, i)),
+                    });
+                    child_id += size(child);
+                }
+
+                self.choices.extend(alternatives.into_iter().rev());
+                None
+            }
+            Token::GreedyQuantifier(child, min, max) | Token::LazyQuantifier(child, min, max) => {
+                let lp = Loop {
+                    tok: child,
+                    id,
+                    min: *min,
+                    max: *max,
+                    lazy: matches!(tok, Token::LazyQuantifier(..)),
+                };
+
+                self.repeat(lp, 0, pos, true, k)
+            }
+            Token::Capturing(child, _) => Some(Goal::Token(
+                child,
+                id + 1,
+                pos,
+                push(FrameKind::Capture(id, pos), k),
+            )),
+            _ => match token_ranges(tok) {
+                Some(ranges) if pos < self.chars.len() && contains(&ranges, self.chars[pos]) => {
+                    Some(matched(self, pos + 1))
+                }
+                _ => None,
+            },
+        })
+    }
+
+    /// The goal after reaching `frame` at `pos`, or `None` when the next
+    /// choice is to be tried.
+    fn rest(&mut self, pos: usize, frame: &Frame<'t>) -> Option<Goal<'t>> {
+        let k = frame.next.clone();
+
+        match frame.kind {
+            FrameKind::Sequence([], _) => Some(Goal::Rest(pos, k)),
+            FrameKind::Sequence([first, rest @ ..], id) => Some(Goal::Token(
+                first,
+                id,
+                pos,
+                push(FrameKind::Sequence(rest, id + size(first)), k),
+            )),
+            FrameKind::Repeat(lp, count, start) => self.repeat(lp, count, pos, pos != start, k),
+            FrameKind::Capture(id, start) => {
+                self.steps.push(Step::Captured(id, start, pos));
+                Some(Goal::Rest(pos, k))
+            }
+        }
+    }
+
+    /// Pushes the choices of the quantifier `lp` after its token ran `count`
+    /// times, ending at `pos`: running it again and stopping, in the order
+    /// the quantifier prefers. Once the lower bound is reached, a run that
+    /// matched nothing ends the loop, which would never stop otherwise.
+    fn repeat(
+        &mut self,
+        lp: Loop<'t>,
+        count: usize,
+        pos: usize,
+        progress: bool,
+        k: Continuation<'t>,
+    ) -> Option<Goal<'t>> {
+        let more = lp.max.is_none_or(|max| count < max) && (progress || count < lp.min);
+        let stop = count >= lp.min;
+        let mark = self.steps.len();
+        let again = || Choice {
+            goal: Goal::Token(
+                lp.tok,
+                lp.id + 1,
+                pos,
+                push(FrameKind::Repeat(lp, count + 1, pos), k.clone()),
+            ),
+            mark,
+            step: None,
+        };
+        let done = || Choice {
+            goal: Goal::Rest(pos, k.clone()),
+            mark,
+            step: Some(Step::Repeated(lp.id, count)),
+        };
+        let mut choices = vec![];
+
+        if more {
+            choices.push(again());
+        }
+        if stop {
+            choices.push(done());
+        }
+        if lp.lazy {
+            choices.reverse();
+        }
+        self.choices.extend(choices.into_iter().rev());
+        None
+    }
+}
+
+/// The leftmost match of `tok` in `input` and the path it took, `None` when
+/// there is none, or an error when finding out takes more than
+/// [`STEP_LIMIT`] steps.
+pub fn trace(tok: &Token, input: &str) -> Result<Option<Trace>, StepLimit> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut matcher = Matcher {
+        chars: &chars,
+        steps: vec![],
+        choices: vec![],
+        budget: STEP_LIMIT,
+    };
+
+    for start in 0..=chars.len() {
+        if let Some(end) = matcher.run(Goal::Token(tok, 0, start, None))? {
+            let steps = matcher.steps;
+
+            return Ok(Some(Trace {
+                chars,
+                start,
+                end,
+                steps,
+            }));
+        }
+        matcher.steps.clear();
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    fn run(pattern: &str, input: &str) -> Option<Trace> {
+        trace(&parse_expr(pattern.chars()).expect("parse"), input).expect("within the step limit")
+    }
+
+    #[test]
+    fn test_leftmost() {
+        let trace = run("b+", "abbbc").expect("match");
+
+        assert_eq!((trace.start, trace.end), (1, 4));
+        assert_eq!(trace.matched(), "bbb");
+        assert!(run("^b", "abc").is_none());
+    }
+
+    #[test]
+    fn test_alternatives() {
+        // Conjunction 0, disjunction 1, its alternatives 2 and 4.
+        let trace = run("(?:ab|a)c", "ac").expect("match");
+
+        assert_eq!(trace.alternatives(1), vec![1]);
+        assert_eq!(trace.matched(), "ac");
+    }
+
+    #[test]
+    fn test_greedy_and_lazy() {
+        let greedy = run("a+", "aaa").expect("match");
+        let lazy = run("a+?", "aaa").expect("match");
+
+        assert_eq!(greedy.repeats(1), vec![3]);
+        assert_eq!(lazy.repeats(1), vec![1]);
+        assert_eq!(lazy.matched(), "a");
+    }
+
+    #[test]
+    fn test_captures() {
+        // Quantifier 1, group 2, its conjunction 3 and the digit 4.
+        let trace = run(r"(\d)+x", "123x").expect("match");
+
+        assert_eq!(trace.capture(2), Some((2, 3)));
+        assert_eq!(trace.text(2, 3), "3");
+        assert_eq!(trace.repeats(1), vec![3]);
+    }
+
+    #[test]
+    fn test_backtracking() {
+        let trace = run(r"^(a|ab)(c|bcd)$", "abcd").expect("match");
+
+        assert_eq!(
+            trace.capture(2).map(|(s, e)| trace.text(s, e)),
+            Some("a".to_owned())
+        );
+        assert!(run(r"\bcat\b", "concat").is_none());
+        assert!(run(r"\bcat\b", "a cat").is_some());
+    }
+
+    #[test]
+    fn test_empty_loop() {
+        let trace = run("(?:a*)*b", "b").expect("match");
+
+        assert_eq!(trace.matched(), "b");
+    }
+
+    #[test]
+    fn test_long_input() {
+        let trace = run("a*b", &("a".repeat(100_000) + "b")).expect("match");
+
+        assert_eq!(trace.repeats(1), vec![100_000]);
+        assert_eq!(trace.end, 100_001);
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let tok = parse_expr("(a+)+b|c".chars()).expect("parse");
+
+        assert_eq!(trace(&tok, &("a".repeat(25) + "c")), Err(StepLimit));
+        assert!(run("(a+)+b|c", "aac").is_some());
+    }
+}